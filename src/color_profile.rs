@@ -122,6 +122,7 @@ impl ColorProfile for ColorProfileRaw {
     }
 }
 
+#[derive(Debug)]
 pub struct ColorProfileNCLX {
     pub(crate) inner: *mut lh::heif_color_profile_nclx,
 }
@@ -175,15 +176,31 @@ impl ColorProfileNCLX {
             .unwrap_or(TransferCharacteristics::Unknown)
     }
 
+    pub fn set_transfer_characteristics(&mut self, v: TransferCharacteristics) {
+        if v != TransferCharacteristics::Unknown {
+            self.inner_mut().transfer_characteristics = v as _;
+        }
+    }
+
     pub fn matrix_coefficients(&self) -> MatrixCoefficients {
         MatrixCoefficients::n(self.inner_ref().matrix_coefficients)
             .unwrap_or(MatrixCoefficients::Unknown)
     }
 
+    pub fn set_matrix_coefficients(&mut self, v: MatrixCoefficients) {
+        if v != MatrixCoefficients::Unknown {
+            self.inner_mut().matrix_coefficients = v as _;
+        }
+    }
+
     pub fn full_range_flag(&self) -> u8 {
         self.inner_ref().full_range_flag
     }
 
+    pub fn set_full_range_flag(&mut self, v: u8) {
+        self.inner_mut().full_range_flag = v;
+    }
+
     pub fn color_primary_red_x(&self) -> f32 {
         self.inner_ref().color_primary_red_x
     }
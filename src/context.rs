@@ -1,24 +1,55 @@
-use std::ffi;
+use std::io;
 use std::os::raw::c_void;
+use std::path::Path;
 use std::ptr;
+use std::slice;
 
 use four_cc::FourCC;
 use libheif_sys as lh;
 
-use crate::reader::{Reader, HEIF_READER};
-use crate::utils::str_to_cstring;
+use crate::reader::{Reader, StreamReader, HEIF_READER};
+use crate::utils::{path_to_cstring, str_to_cstring};
+use crate::writer::{heif_writer, IoWriter, WriterState};
 use crate::{
-    Encoder, EncodingOptions, HeifError, HeifErrorCode, HeifErrorSubCode, Image, ImageHandle,
-    ItemId, Result,
+    AuxiliaryImagesFilter, Encoder, EncodingOptions, HeifError, HeifErrorCode, HeifErrorSubCode,
+    Image, ImageHandle, ItemId, Result, Writer,
 };
 
 enum Source<'a> {
     None,
     File,
     Memory(&'a [u8]),
+    // The bytes are never read from Rust; they are only kept alive
+    // while `libheif` reads them.
+    #[allow(dead_code)]
+    Owned(Vec<u8>),
     Reader(Box<Box<dyn Reader>>),
 }
 
+/// Counts of items in a [`HeifContext`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ContextStats {
+    pub top_level_images: usize,
+    pub thumbnails: usize,
+    /// Auxiliary images, including alpha channel and depth images.
+    pub auxiliary_images: usize,
+    pub metadata_blocks: usize,
+    /// Total size of data of all metadata blocks in bytes.
+    pub metadata_size: usize,
+}
+
+/// Container of images and metadata in HEIF format.
+///
+/// `HeifContext` implements `Send`, but it is not `Sync`.
+/// `libheif` reads image data lazily from the source of context
+/// (file, memory or [`Reader`]) while decoding, and this access
+/// is not synchronized. So it is not safe to use one context
+/// from several threads at the same time, even for reading only.
+///
+/// To decode images in parallel, read a separate context in each thread
+/// (data in memory can be shared between contexts created by
+/// [`HeifContext::read_from_bytes`]), or wrap the context
+/// into [`std::sync::Mutex`] to serialize access to it.
 pub struct HeifContext<'a> {
     pub(crate) inner: *mut lh::heif_context,
     source: Source<'a>,
@@ -43,10 +74,14 @@ impl HeifContext<'static> {
     }
 
     /// Create a new context from file.
-    pub fn read_from_file(name: &str) -> Result<HeifContext<'static>> {
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<HeifContext<'static>> {
+        Self::_read_from_file(path.as_ref())
+    }
+
+    fn _read_from_file(path: &Path) -> Result<HeifContext<'static>> {
         let mut context = HeifContext::new()?;
         context.source = Source::File;
-        let c_name = ffi::CString::new(name).unwrap();
+        let c_name = path_to_cstring(path)?;
         let err =
             unsafe { lh::heif_context_read_from_file(context.inner, c_name.as_ptr(), ptr::null()) };
         HeifError::from_heif_error(err)?;
@@ -65,6 +100,59 @@ impl HeifContext<'static> {
         context.source = Source::Reader(reader_box);
         Ok(context)
     }
+
+    /// Create a new context from a stream that implements
+    /// [`io::Read`] and [`io::Seek`].
+    ///
+    /// Total size of the stream is determined by seeking to its end.
+    pub fn read_from_io<R>(mut stream: R) -> Result<HeifContext<'static>>
+    where
+        R: io::Read + io::Seek + 'static,
+    {
+        let total_size = stream
+            .seek(io::SeekFrom::End(0))
+            .and_then(|size| stream.seek(io::SeekFrom::Start(0)).map(|_| size))
+            .map_err(|err| HeifError {
+                code: HeifErrorCode::InvalidInput,
+                sub_code: HeifErrorSubCode::Unspecified,
+                message: format!("Failed to get size of stream: {}", err),
+            })?;
+        Self::read_from_reader(Box::new(StreamReader::new(stream, total_size)))
+    }
+}
+
+impl Default for HeifContext<'static> {
+    /// Create a new empty context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `libheif` can't allocate a new context.
+    /// Use [`HeifContext::new()`] to handle this error.
+    fn default() -> Self {
+        Self::new().expect("heif_context_alloc() returns a null pointer")
+    }
+}
+
+impl HeifContext<'static> {
+    /// Create a new context from bytes.
+    ///
+    /// The context takes ownership of the provided buffer,
+    /// so data is not copied.
+    pub fn read_from_bytes_owned(bytes: Vec<u8>) -> Result<HeifContext<'static>> {
+        let mut context = HeifContext::new()?;
+        let err = unsafe {
+            lh::heif_context_read_from_memory_without_copy(
+                context.inner,
+                bytes.as_ptr() as _,
+                bytes.len(),
+                ptr::null(),
+            )
+        };
+        // Moving of vector doesn't move its heap buffer.
+        context.source = Source::Owned(bytes);
+        HeifError::from_heif_error(err)?;
+        Ok(context)
+    }
 }
 
 impl<'a> HeifContext<'a> {
@@ -72,8 +160,10 @@ impl<'a> HeifContext<'a> {
     ///
     /// The provided memory buffer is not copied.
     /// That means, you will have to keep the memory buffer alive as
-    /// long as you use the context.
-    pub fn read_from_bytes(bytes: &[u8]) -> Result<HeifContext> {
+    /// long as you use the context (the compiler checks this with help of
+    /// lifetime of the context). Use [`HeifContext::read_from_bytes_owned`]
+    /// to pass ownership of the buffer to the context.
+    pub fn read_from_bytes(bytes: &'a [u8]) -> Result<HeifContext<'a>> {
         let mut context = HeifContext::new()?;
         context.source = Source::Memory(bytes);
         let err = unsafe {
@@ -88,6 +178,22 @@ impl<'a> HeifContext<'a> {
         Ok(context)
     }
 
+    /// Raw pointer to the underlying `heif_context` for direct
+    /// calls of `libheif-sys` functions that are not wrapped yet.
+    ///
+    /// The pointer is owned by this context: don't free it and
+    /// don't use it after the context is dropped.
+    pub fn as_raw(&self) -> *const lh::heif_context {
+        self.inner
+    }
+
+    /// Mutable raw pointer to the underlying `heif_context`.
+    ///
+    /// See [`HeifContext::as_raw()`] about restrictions.
+    pub fn as_raw_mut(&mut self) -> *mut lh::heif_context {
+        self.inner
+    }
+
     unsafe extern "C" fn vector_writer(
         _ctx: *mut lh::heif_context,
         data: *const c_void,
@@ -95,9 +201,9 @@ impl<'a> HeifContext<'a> {
         user_data: *mut c_void,
     ) -> lh::heif_error {
         let vec: &mut Vec<u8> = &mut *(user_data as *mut Vec<u8>);
-        vec.reserve(size);
-        ptr::copy_nonoverlapping::<u8>(data as _, vec.as_mut_ptr(), size);
-        vec.set_len(size);
+        if size > 0 {
+            vec.extend_from_slice(slice::from_raw_parts(data as *const u8, size));
+        }
 
         lh::heif_error {
             code: lh::heif_error_code_heif_error_Ok,
@@ -120,8 +226,37 @@ impl<'a> HeifContext<'a> {
         Ok(res)
     }
 
-    pub fn write_to_file(&self, name: &str) -> Result<()> {
-        let c_name = ffi::CString::new(name).unwrap();
+    /// Write the context into the given [`Writer`].
+    ///
+    /// If the writer fails, writing is aborted and the error
+    /// returned by the writer is returned.
+    pub fn write_to_writer(&self, writer: &mut dyn Writer) -> Result<()> {
+        let mut state = WriterState::new(writer);
+        let user_data = &mut state as *mut WriterState as *mut c_void;
+        let mut c_writer = heif_writer();
+        let err = unsafe { lh::heif_context_write(self.inner, &mut c_writer, user_data) };
+        if let Some(writer_err) = state.error.take() {
+            return Err(writer_err);
+        }
+        HeifError::from_heif_error(err)
+    }
+
+    /// Write the context into the given [`io::Write`] sink.
+    ///
+    /// Errors of the sink are returned as [`HeifError`] with
+    /// [`HeifErrorSubCode::CannotWriteOutputData`] sub-code.
+    pub fn write_to_io<W: io::Write>(&self, sink: &mut W) -> Result<()> {
+        let mut writer = IoWriter::new(sink);
+        self.write_to_writer(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        self._write_to_file(path.as_ref())
+    }
+
+    fn _write_to_file(&self, path: &Path) -> Result<()> {
+        let c_name = path_to_cstring(path)?;
         let err = unsafe { lh::heif_context_write_to_file(self.inner, c_name.as_ptr()) };
         HeifError::from_heif_error(err)
     }
@@ -130,6 +265,8 @@ impl<'a> HeifContext<'a> {
         unsafe { lh::heif_context_get_number_of_top_level_images(self.inner) as _ }
     }
 
+    /// Fill the given slice with IDs of top-level images.
+    /// Returns the number of IDs written into the slice.
     pub fn top_level_image_ids(&self, item_ids: &mut [ItemId]) -> usize {
         if item_ids.is_empty() {
             0
@@ -144,6 +281,10 @@ impl<'a> HeifContext<'a> {
         }
     }
 
+    /// Get a handle of the top-level image with the given ID.
+    ///
+    /// Returned handle doesn't borrow the context, but you MUST NOT use it
+    /// after the context is dropped.
     pub fn image_handle(&self, item_id: ItemId) -> Result<ImageHandle> {
         let mut handle: *mut lh::heif_image_handle = ptr::null_mut();
         let err = unsafe { lh::heif_context_get_image_handle(self.inner, item_id, &mut handle) };
@@ -158,6 +299,10 @@ impl<'a> HeifContext<'a> {
         Ok(ImageHandle::new(handle))
     }
 
+    /// Get handles of all top-level images.
+    ///
+    /// As with [`HeifContext::image_handle()`], you MUST NOT use returned
+    /// handles after the context is dropped.
     pub fn top_level_image_handles(&self) -> Vec<ImageHandle> {
         let max_count = self.number_of_top_level_images();
         let mut item_ids = Vec::with_capacity(max_count);
@@ -178,14 +323,71 @@ impl<'a> HeifContext<'a> {
         handles
     }
 
+    /// Count items attached to top-level images of the context.
+    ///
+    /// Items are counted without decoding of images.
+    /// `libheif` doesn't provide the size of coded image data,
+    /// so it is not included.
+    pub fn stats(&self) -> ContextStats {
+        let mut stats = ContextStats::default();
+        for handle in self.top_level_image_handles() {
+            stats.top_level_images += 1;
+            stats.thumbnails += handle.number_of_thumbnails();
+            stats.auxiliary_images +=
+                handle.number_of_auxiliary_images(AuxiliaryImagesFilter::default());
+            let count = handle.number_of_metadata_blocks(0).max(0) as usize;
+            let mut meta_ids = vec![0; count];
+            let count = handle.metadata_block_ids(&mut meta_ids, 0);
+            stats.metadata_blocks += count;
+            stats.metadata_size += meta_ids[..count]
+                .iter()
+                .map(|&id| handle.metadata_size(id))
+                .sum::<usize>();
+        }
+        stats
+    }
+
     /// Compress the input image.
     /// The first image added to the context is also automatically set as the primary image, but
     /// you can change the primary image later with [`HeifContext::set_primary_image`] method.
+    ///
+    /// The image may have any color space. If the encoder doesn't support
+    /// it as input (e.g. HEVC and AV1 encoders accept only YCbCr and
+    /// monochrome images), `libheif` converts the image before encoding.
+    /// So decoded YCbCr image is encoded without conversion by these encoders.
     pub fn encode_image(
         &mut self,
         image: &Image,
         encoder: &mut Encoder,
         encoding_options: Option<EncodingOptions>,
+    ) -> Result<ImageHandle> {
+        self._encode_image(image, encoder, encoding_options.as_ref())
+    }
+
+    /// Compress the given images in order and return their handles.
+    ///
+    /// The first of the images is set as the primary image.
+    pub fn encode_images(
+        &mut self,
+        images: &[Image],
+        encoder: &mut Encoder,
+        encoding_options: Option<EncodingOptions>,
+    ) -> Result<Vec<ImageHandle>> {
+        let handles = images
+            .iter()
+            .map(|image| self._encode_image(image, encoder, encoding_options.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(handle) = handles.first() {
+            self.set_primary_image(handle)?;
+        }
+        Ok(handles)
+    }
+
+    fn _encode_image(
+        &mut self,
+        image: &Image,
+        encoder: &mut Encoder,
+        encoding_options: Option<&EncodingOptions>,
     ) -> Result<ImageHandle> {
         let encoding_options_ptr = match encoding_options {
             Some(options) => options.inner,
@@ -222,7 +424,7 @@ impl<'a> HeifContext<'a> {
         encoder: &mut Encoder,
         encoding_options: Option<EncodingOptions>,
     ) -> Result<Option<ImageHandle>> {
-        let encoding_options_ptr = match encoding_options {
+        let encoding_options_ptr = match &encoding_options {
             Some(options) => options.inner,
             None => ptr::null(),
         };
@@ -239,6 +441,9 @@ impl<'a> HeifContext<'a> {
             );
             HeifError::from_heif_error(err)?;
         }
+        if handle.is_null() {
+            return Ok(None);
+        }
         Ok(Some(ImageHandle::new(handle)))
     }
 
@@ -258,7 +463,10 @@ impl<'a> HeifContext<'a> {
         }
     }
 
-    pub fn set_primary_image(&mut self, image_handle: &mut ImageHandle) -> Result<()> {
+    /// Mark the image with given handle as the primary image of the file.
+    ///
+    /// By default, the first encoded image is the primary one.
+    pub fn set_primary_image(&mut self, image_handle: &ImageHandle) -> Result<()> {
         unsafe {
             let err = lh::heif_context_set_primary_image(self.inner, image_handle.inner);
             HeifError::from_heif_error(err)
@@ -287,7 +495,10 @@ impl<'a> HeifContext<'a> {
             Some(s) => Some(str_to_cstring(s, "content_type")?),
             None => None,
         };
-        let c_content_type_ptr = c_content_type.map(|s| s.as_ptr()).unwrap_or(ptr::null());
+        let c_content_type_ptr = c_content_type
+            .as_ref()
+            .map(|s| s.as_ptr())
+            .unwrap_or(ptr::null());
         let error = unsafe {
             lh::heif_context_add_generic_metadata(
                 self.inner,
@@ -302,6 +513,10 @@ impl<'a> HeifContext<'a> {
     }
 
     /// Add EXIF metadata to an image.
+    ///
+    /// `data` must be EXIF in TIFF format (starting with `II` or `MM`), like
+    /// the one returned by [`ImageHandle::exif_metadata()`]. The offset header
+    /// required by HEIF is added by `libheif`.
     pub fn add_exif_metadata(&mut self, master_image: &ImageHandle, data: &[u8]) -> Result<()> {
         let error = unsafe {
             lh::heif_context_add_exif_metadata(
@@ -339,6 +554,20 @@ impl<'a> HeifContext<'a> {
         let max_threads = max_threads.min(libc::c_int::MAX as u32) as libc::c_int;
         unsafe { lh::heif_context_set_max_decoding_threads(self.inner, max_threads) };
     }
+
+    /// Set the maximum width and height of images.
+    /// Images that exceed this limit in any dimension are rejected
+    /// with [`HeifErrorSubCode::SecurityLimitExceeded`] error.
+    ///
+    /// By default, `libheif` limits the image size to 32768x32768 pixels.
+    ///
+    /// Note that `libheif` checks this limit while interpreting
+    /// image items of a file, so it does not affect images of a file
+    /// which has already been read into the context.
+    pub fn set_max_image_size_limit(&mut self, max_size: u32) {
+        let max_size = max_size.min(libc::c_int::MAX as u32) as libc::c_int;
+        unsafe { lh::heif_context_set_maximum_image_size_limit(self.inner, max_size) };
+    }
 }
 
 impl<'a> Drop for HeifContext<'a> {
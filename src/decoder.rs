@@ -8,10 +8,98 @@ use std::sync::Mutex;
 
 static DECODER_MUTEX: Mutex<()> = Mutex::new(());
 
+/// Step of decoding reported to a progress callback.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ProgressStep {
+    Total,
+    LoadTile,
+    /// This value is used when library `libheif` returns unknown step.
+    Unknown,
+}
+
+impl ProgressStep {
+    fn from_libheif(step: lh::heif_progress_step) -> Self {
+        match step {
+            lh::heif_progress_step_heif_progress_step_total => ProgressStep::Total,
+            lh::heif_progress_step_heif_progress_step_load_tile => ProgressStep::LoadTile,
+            _ => ProgressStep::Unknown,
+        }
+    }
+}
+
+/// Event passed into a progress callback of decoding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ProgressEvent {
+    /// Step has started. `max_progress` is the value of progress
+    /// when the step will be finished.
+    Start {
+        step: ProgressStep,
+        max_progress: i32,
+    },
+    Progress {
+        step: ProgressStep,
+        progress: i32,
+    },
+    End {
+        step: ProgressStep,
+    },
+}
+
+type ProgressCallback = Box<dyn FnMut(ProgressEvent)>;
+
+/// Options of decoding.
+///
+/// Note: `libheif` always decodes images in full resolution.
+/// To get a fast preview, decode a thumbnail of the image
+/// (see [`ImageHandle::thumbnails()`](crate::ImageHandle::thumbnails))
+/// if the file has one, or scale the decoded image with
+/// [`Image::scale()`](crate::Image::scale).
 #[derive(Debug)]
 pub struct DecodingOptions {
     pub(crate) inner: *mut lh::heif_decoding_options,
     decoder_id: Option<CString>,
+    progress_callback: Option<ProgressCallbackBox>,
+}
+
+/// Wrapper to store the callback in a thin pointer
+/// that is passed into `libheif` as user data.
+struct ProgressCallbackBox(Box<ProgressCallback>);
+
+impl Debug for ProgressCallbackBox {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+unsafe extern "C" fn start_progress(
+    step: lh::heif_progress_step,
+    max_progress: libc::c_int,
+    user_data: *mut libc::c_void,
+) {
+    let callback = &mut *(user_data as *mut ProgressCallback);
+    callback(ProgressEvent::Start {
+        step: ProgressStep::from_libheif(step),
+        max_progress,
+    });
+}
+
+unsafe extern "C" fn on_progress(
+    step: lh::heif_progress_step,
+    progress: libc::c_int,
+    user_data: *mut libc::c_void,
+) {
+    let callback = &mut *(user_data as *mut ProgressCallback);
+    callback(ProgressEvent::Progress {
+        step: ProgressStep::from_libheif(step),
+        progress,
+    });
+}
+
+unsafe extern "C" fn end_progress(step: lh::heif_progress_step, user_data: *mut libc::c_void) {
+    let callback = &mut *(user_data as *mut ProgressCallback);
+    callback(ProgressEvent::End {
+        step: ProgressStep::from_libheif(step),
+    });
 }
 
 impl DecodingOptions {
@@ -23,6 +111,7 @@ impl DecodingOptions {
         Some(Self {
             inner,
             decoder_id: None,
+            progress_callback: None,
         })
     }
 }
@@ -51,6 +140,8 @@ impl DecodingOptions {
         self.inner_ref().version
     }
 
+    /// Ignore geometric transformations like cropping, rotation, mirroring.
+    /// Default is `false` (do not ignore).
     #[inline]
     pub fn ignore_transformations(&self) -> bool {
         self.inner_ref().ignore_transformations != 0
@@ -66,6 +157,10 @@ impl DecodingOptions {
         self.inner_ref().convert_hdr_to_8bit != 0
     }
 
+    /// Convert images with more than 8 bits per pixel into 8-bit images.
+    ///
+    /// This is a naive conversion that just drops the least
+    /// significant bits, without any tone-mapping.
     #[inline]
     pub fn set_convert_hdr_to_8bit(&mut self, enable: bool) {
         self.inner_mut().convert_hdr_to_8bit = if enable { 1 } else { 0 }
@@ -101,6 +196,32 @@ impl DecodingOptions {
         Ok(())
     }
 
+    /// Set a callback that receives progress of decoding.
+    ///
+    /// Currently, `libheif` reports progress only while decoding grid
+    /// images: [`ProgressStep::Total`] step with one progress event
+    /// per decoded tile. Decoding can't be cancelled from the callback.
+    pub fn set_progress_callback(&mut self, callback: Option<Box<dyn FnMut(ProgressEvent)>>) {
+        let mut callback_box = callback.map(|c| ProgressCallbackBox(Box::new(c)));
+        let inner = self.inner_mut();
+        match callback_box.as_mut() {
+            Some(callback_box) => {
+                inner.start_progress = Some(start_progress);
+                inner.on_progress = Some(on_progress);
+                inner.end_progress = Some(end_progress);
+                inner.progress_user_data =
+                    callback_box.0.as_mut() as *mut ProgressCallback as *mut _;
+            }
+            None => {
+                inner.start_progress = None;
+                inner.on_progress = None;
+                inner.end_progress = None;
+                inner.progress_user_data = ptr::null_mut();
+            }
+        }
+        self.progress_callback = callback_box;
+    }
+
     pub fn color_conversion_options(&self) -> ColorConversionOptions {
         let lh_options = self.inner_ref().color_conversion_options;
         ColorConversionOptions {
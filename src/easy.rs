@@ -0,0 +1,45 @@
+//! Easy path: functions to encode and decode images with
+//! default settings by one call.
+
+use crate::{
+    Channel, ColorSpace, CompressionFormat, EncoderQuality, HeifContext, Image, LibHeif, Result,
+    RgbChroma,
+};
+
+/// Encode an image from interleaved 8-bit RGB pixels into bytes
+/// of a file with the given compression format.
+///
+/// `rgb` must contain `height` rows of `width * 3` bytes each.
+/// `quality` is a lossy quality factor (0-100).
+///
+/// Use [`HeifContext`] and [`LibHeif::encoder_for_format()`]
+/// if you need more control over encoding.
+pub fn encode_rgb(
+    width: u32,
+    height: u32,
+    rgb: &[u8],
+    format: CompressionFormat,
+    quality: u8,
+) -> Result<Vec<u8>> {
+    let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgb))?;
+    image.create_plane(Channel::Interleaved, width, height, 8)?;
+    image.write_plane(Channel::Interleaved, rgb, width as usize * 3)?;
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(format)?;
+    encoder.set_quality(EncoderQuality::Lossy(quality))?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    context.write_to_bytes()
+}
+
+/// Decode the primary image of a file into an image with
+/// interleaved 8-bit RGB pixels.
+///
+/// Use [`HeifContext`] and [`LibHeif::decode()`]
+/// if you need more control over decoding.
+pub fn decode_first_image(bytes: &[u8]) -> Result<Image> {
+    let context = HeifContext::read_from_bytes(bytes)?;
+    let handle = context.primary_image_handle()?;
+    LibHeif::new().decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+}
@@ -1,15 +1,16 @@
 use std::collections::HashMap;
-use std::ffi::CString;
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
+use std::ptr;
+use std::slice;
 use std::sync::Mutex;
 
 use libheif_sys as lh;
 
-use crate::utils::cstr_to_str;
+use crate::utils::{cstr_to_str, str_to_cstring};
 use crate::{
-    ChromaDownsamplingAlgorithm, ChromaUpsamplingAlgorithm, ColorConversionOptions, HeifError,
-    HeifErrorCode, HeifErrorSubCode, ImageOrientation, Result,
+    ChromaDownsamplingAlgorithm, ChromaUpsamplingAlgorithm, ColorConversionOptions,
+    ColorProfileNCLX, HeifError, HeifErrorCode, HeifErrorSubCode, ImageOrientation, Result,
 };
 
 static ENCODER_MUTEX: Mutex<()> = Mutex::new(());
@@ -52,6 +53,68 @@ pub enum EncoderQuality {
     Lossy(u8),
 }
 
+/// Speed of encoding that doesn't depend on the used encoder.
+///
+/// Slower encoding usually gives smaller files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EncoderSpeed {
+    Slowest,
+    Slow,
+    Medium,
+    Fast,
+    Fastest,
+}
+
+impl EncoderSpeed {
+    /// Value of the `preset` parameter of x265 encoder.
+    fn x265_preset(self) -> &'static str {
+        match self {
+            EncoderSpeed::Slowest => "veryslow",
+            EncoderSpeed::Slow => "slow",
+            EncoderSpeed::Medium => "medium",
+            EncoderSpeed::Fast => "fast",
+            EncoderSpeed::Fastest => "ultrafast",
+        }
+    }
+
+    /// Value of the integer `speed` parameter
+    /// of AV1 encoders (aom, rav1e, svt).
+    fn speed_in_range(self, minimum: i32, maximum: i32) -> i32 {
+        let level = match self {
+            EncoderSpeed::Slowest => 0,
+            EncoderSpeed::Slow => 1,
+            EncoderSpeed::Medium => 2,
+            EncoderSpeed::Fast => 3,
+            EncoderSpeed::Fastest => 4,
+        };
+        minimum + (maximum - minimum) * level / 4
+    }
+}
+
+/// Values accepted by an encoder's parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EncoderParameterValidValues {
+    /// Encoder doesn't report any restrictions for the parameter.
+    Any,
+    /// Integer value limited by a range.
+    IntRange {
+        minimum: Option<i32>,
+        maximum: Option<i32>,
+    },
+    /// Integer value limited by a fixed set of values.
+    Ints(Vec<i32>),
+    /// String value limited by a fixed set of values.
+    Strings(Vec<String>),
+}
+
+/// Description of an encoder's parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EncoderParameter {
+    pub name: String,
+    pub parameter_type: EncoderParameterType,
+    pub valid_values: EncoderParameterValidValues,
+}
+
 pub type EncoderParametersTypes = HashMap<String, EncoderParameterType>;
 
 pub struct Encoder<'a> {
@@ -77,6 +140,12 @@ impl<'a> Drop for Encoder<'a> {
     }
 }
 
+// Instances of encoders hold no thread-local state,
+// so they can be moved into other threads.
+// But they are not `Sync`, because encoding with one instance
+// from several threads at the same time is not safe.
+unsafe impl<'a> Send for Encoder<'a> {}
+
 impl<'a> Encoder<'a> {
     /// Name of encoder.
     pub fn name(&self) -> String {
@@ -87,7 +156,39 @@ impl<'a> Encoder<'a> {
         cstr_to_str(res).unwrap_or("").to_owned()
     }
 
+    /// Returns `true` if the encoder supports lossless compression.
+    ///
+    /// It is detected by presence of boolean parameter `lossless`.
+    pub fn supports_lossless(&self) -> bool {
+        self.parameters_types.get("lossless") == Some(&EncoderParameterType::Bool)
+    }
+
+    /// Set quality of encoding.
+    ///
+    /// Returns error if value of [`EncoderQuality::Lossy`]
+    /// is greater than 100, or if [`EncoderQuality::LossLess`]
+    /// is not supported by the encoder.
     pub fn set_quality(&mut self, quality: EncoderQuality) -> Result<()> {
+        match quality {
+            EncoderQuality::Lossy(value) if value > 100 => {
+                return Err(HeifError {
+                    code: HeifErrorCode::UsageError,
+                    sub_code: HeifErrorSubCode::InvalidParameterValue,
+                    message: format!("Invalid value of lossy quality: {} (must be 0-100)", value),
+                });
+            }
+            EncoderQuality::LossLess if !self.supports_lossless() => {
+                return Err(HeifError {
+                    code: HeifErrorCode::UsageError,
+                    sub_code: HeifErrorSubCode::UnsupportedParameter,
+                    message: format!(
+                        "Encoder '{}' doesn't support lossless compression",
+                        self.name()
+                    ),
+                });
+            }
+            _ => {}
+        }
         let err = match quality {
             EncoderQuality::LossLess => unsafe { lh::heif_encoder_set_lossless(self.inner, 1) },
             EncoderQuality::Lossy(value) => unsafe {
@@ -99,12 +200,79 @@ impl<'a> Encoder<'a> {
         HeifError::from_heif_error(err)
     }
 
+    /// Set speed of encoding.
+    ///
+    /// The speed is translated into the value of encoder specific
+    /// parameter: `preset` of x265 encoder or `speed` of AV1 encoders.
+    /// Returns error if the encoder has no such parameters.
+    pub fn set_speed(&mut self, speed: EncoderSpeed) -> Result<()> {
+        let unsupported = |name: String| HeifError {
+            code: HeifErrorCode::UsageError,
+            sub_code: HeifErrorSubCode::UnsupportedParameter,
+            message: format!("Encoder '{}' doesn't support setting of speed", name),
+        };
+        let parameters = self.list_parameters();
+        let preset = parameters
+            .iter()
+            .find(|p| p.name == "preset" && p.parameter_type == EncoderParameterType::String);
+        if let Some(param) = preset {
+            let value = speed.x265_preset();
+            return match &param.valid_values {
+                EncoderParameterValidValues::Strings(values)
+                    if !values.iter().any(|v| v == value) =>
+                {
+                    Err(unsupported(self.name()))
+                }
+                _ => self.set_parameter_value(
+                    "preset",
+                    EncoderParameterValue::String(value.to_string()),
+                ),
+            };
+        }
+        let speed_param = parameters
+            .iter()
+            .find(|p| p.name == "speed" && p.parameter_type == EncoderParameterType::Int);
+        let range = speed_param.and_then(|param| match &param.valid_values {
+            EncoderParameterValidValues::IntRange {
+                minimum: Some(minimum),
+                maximum: Some(maximum),
+            } => Some((*minimum, *maximum)),
+            EncoderParameterValidValues::Ints(values) => {
+                Some((*values.iter().min()?, *values.iter().max()?))
+            }
+            _ => None,
+        });
+        match range {
+            Some((minimum, maximum)) => self.set_parameter_value(
+                "speed",
+                EncoderParameterValue::Int(speed.speed_in_range(minimum, maximum)),
+            ),
+            None => Err(unsupported(self.name())),
+        }
+    }
+
+    /// Set verbosity of logging of the encoder,
+    /// from 0 (no logging) to 4 (full logging).
+    ///
+    /// Encoders that don't support logging ignore this setting.
+    pub fn set_logging_level(&mut self, level: u8) -> Result<()> {
+        if level > 4 {
+            return Err(HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::InvalidParameterValue,
+                message: format!("Invalid logging level: {} (must be 0-4)", level),
+            });
+        }
+        let err = unsafe { lh::heif_encoder_set_logging_level(self.inner, level.into()) };
+        HeifError::from_heif_error(err)
+    }
+
     fn parameter_value(
         &self,
         name: &str,
         parameter_type: EncoderParameterType,
     ) -> Result<EncoderParameterValue> {
-        let c_param_name = CString::new(name).unwrap();
+        let c_param_name = str_to_cstring(name, "name")?;
         let param_value = match parameter_type {
             EncoderParameterType::Int => {
                 let mut value = 0;
@@ -150,6 +318,40 @@ impl<'a> Encoder<'a> {
         Ok(param_value)
     }
 
+    /// List of parameters supported by the encoder with their types
+    /// and valid values.
+    pub fn list_parameters(&self) -> Vec<EncoderParameter> {
+        let mut res = Vec::new();
+        unsafe {
+            let mut param_pointers = lh::heif_encoder_list_parameters(self.inner);
+            if param_pointers.is_null() {
+                return res;
+            }
+            while let Some(raw_param) = (*param_pointers).as_ref() {
+                param_pointers = param_pointers.offset(1);
+                let c_param_type = lh::heif_encoder_parameter_get_type(raw_param);
+                // Types of all parameters were checked while creating the encoder.
+                let parameter_type = match EncoderParameterType::n(c_param_type) {
+                    Some(res) => res,
+                    None => continue,
+                };
+                let c_param_name = lh::heif_encoder_parameter_get_name(raw_param);
+                let name = cstr_to_str(c_param_name).unwrap_or("").to_string();
+                let valid_values = match parameter_type {
+                    EncoderParameterType::Int => valid_integer_values(raw_param),
+                    EncoderParameterType::Bool => EncoderParameterValidValues::Any,
+                    EncoderParameterType::String => valid_string_values(raw_param),
+                };
+                res.push(EncoderParameter {
+                    name,
+                    parameter_type,
+                    valid_values,
+                });
+            }
+        }
+        res
+    }
+
     pub fn parameters_names(&self) -> Vec<String> {
         self.parameters_types.keys().cloned().collect()
     }
@@ -166,8 +368,8 @@ impl<'a> Encoder<'a> {
     }
 
     /// Set value of encoder's parameter.
-    pub fn set_parameter_value(&self, name: &str, value: EncoderParameterValue) -> Result<()> {
-        let c_param_name = CString::new(name).unwrap();
+    pub fn set_parameter_value(&mut self, name: &str, value: EncoderParameterValue) -> Result<()> {
+        let c_param_name = str_to_cstring(name, "name")?;
         let err = match value {
             EncoderParameterValue::Bool(v) => unsafe {
                 lh::heif_encoder_set_parameter_boolean(self.inner, c_param_name.as_ptr(), v.into())
@@ -175,18 +377,41 @@ impl<'a> Encoder<'a> {
             EncoderParameterValue::Int(v) => unsafe {
                 lh::heif_encoder_set_parameter_integer(self.inner, c_param_name.as_ptr(), v)
             },
-            EncoderParameterValue::String(v) => unsafe {
-                let c_param_value = CString::new(v).unwrap();
-                lh::heif_encoder_set_parameter_string(
-                    self.inner,
-                    c_param_name.as_ptr(),
-                    c_param_value.as_ptr(),
-                )
-            },
+            EncoderParameterValue::String(v) => {
+                let c_param_value = str_to_cstring(&v, "value")?;
+                unsafe {
+                    lh::heif_encoder_set_parameter_string(
+                        self.inner,
+                        c_param_name.as_ptr(),
+                        c_param_value.as_ptr(),
+                    )
+                }
+            }
         };
         HeifError::from_heif_error(err)?;
         Ok(())
     }
+
+    /// Set a parameter of any type to the string value.
+    /// Integer values are parsed from the string.
+    /// Boolean values can be "true"/"false"/"1"/"0".
+    ///
+    /// When using the x265 encoder, you may pass any of its parameters by
+    /// prefixing the parameter name with 'x265:'. Hence, to set the 'ctu' parameter,
+    /// you will have to set 'x265:ctu'.
+    /// Note that there is no checking for valid parameters when using the prefix.
+    pub fn set_parameter(&mut self, name: &str, value: &str) -> Result<()> {
+        let c_param_name = str_to_cstring(name, "name")?;
+        let c_param_value = str_to_cstring(value, "value")?;
+        let err = unsafe {
+            lh::heif_encoder_set_parameter(
+                self.inner,
+                c_param_name.as_ptr(),
+                c_param_value.as_ptr(),
+            )
+        };
+        HeifError::from_heif_error(err)
+    }
 }
 
 fn parameters_types(c_encoder: &mut lh::heif_encoder) -> Result<EncoderParametersTypes> {
@@ -216,9 +441,66 @@ fn parameters_types(c_encoder: &mut lh::heif_encoder) -> Result<EncoderParameter
     Ok(res)
 }
 
+unsafe fn valid_integer_values(
+    raw_param: &lh::heif_encoder_parameter,
+) -> EncoderParameterValidValues {
+    let mut have_minimum = 0;
+    let mut have_maximum = 0;
+    let mut minimum = 0;
+    let mut maximum = 0;
+    let mut num_valid_values = 0;
+    let mut valid_values: *const libc::c_int = ptr::null();
+    let err = lh::heif_encoder_parameter_get_valid_integer_values(
+        raw_param,
+        &mut have_minimum,
+        &mut have_maximum,
+        &mut minimum,
+        &mut maximum,
+        &mut num_valid_values,
+        &mut valid_values,
+    );
+    if HeifError::from_heif_error(err).is_err() {
+        return EncoderParameterValidValues::Any;
+    }
+    if num_valid_values > 0 && !valid_values.is_null() {
+        let values = slice::from_raw_parts(valid_values, num_valid_values as usize);
+        EncoderParameterValidValues::Ints(values.to_vec())
+    } else if have_minimum != 0 || have_maximum != 0 {
+        EncoderParameterValidValues::IntRange {
+            minimum: (have_minimum != 0).then_some(minimum),
+            maximum: (have_maximum != 0).then_some(maximum),
+        }
+    } else {
+        EncoderParameterValidValues::Any
+    }
+}
+
+unsafe fn valid_string_values(
+    raw_param: &lh::heif_encoder_parameter,
+) -> EncoderParameterValidValues {
+    let mut c_values: *const *const libc::c_char = ptr::null();
+    let err = lh::heif_encoder_parameter_get_valid_string_values(raw_param, &mut c_values);
+    if HeifError::from_heif_error(err).is_err() || c_values.is_null() {
+        return EncoderParameterValidValues::Any;
+    }
+    let mut values = Vec::new();
+    while !(*c_values).is_null() {
+        values.push(cstr_to_str(*c_values).unwrap_or("").to_string());
+        c_values = c_values.offset(1);
+    }
+    if values.is_empty() {
+        EncoderParameterValidValues::Any
+    } else {
+        EncoderParameterValidValues::Strings(values)
+    }
+}
+
 #[derive(Debug)]
 pub struct EncodingOptions {
     pub(crate) inner: *mut lh::heif_encoding_options,
+    // `libheif` doesn't free this profile with options,
+    // so we keep it alive while options exist.
+    output_nclx_profile: Option<ColorProfileNCLX>,
 }
 
 impl EncodingOptions {
@@ -231,7 +513,10 @@ impl EncodingOptions {
                 message: Default::default(),
             });
         }
-        Ok(Self { inner })
+        Ok(Self {
+            inner,
+            output_nclx_profile: None,
+        })
     }
 }
 
@@ -322,6 +607,23 @@ impl EncodingOptions {
         self.inner_mut().image_orientation = orientation as _;
     }
 
+    /// NCLX color profile written into the output file.
+    #[inline]
+    pub fn output_nclx_profile(&self) -> Option<&ColorProfileNCLX> {
+        self.output_nclx_profile.as_ref()
+    }
+
+    /// Set NCLX color profile written into the output file.
+    ///
+    /// If it is `None`, the profile of the encoded image is used.
+    pub fn set_output_nclx_profile(&mut self, profile: Option<ColorProfileNCLX>) {
+        self.inner_mut().output_nclx_profile = match &profile {
+            Some(profile) => profile.inner,
+            None => ptr::null_mut(),
+        };
+        self.output_nclx_profile = profile;
+    }
+
     pub fn color_conversion_options(&self) -> ColorConversionOptions {
         let lh_options = self.inner_ref().color_conversion_options;
         ColorConversionOptions {
@@ -167,3 +167,17 @@ pub enum ChromaUpsamplingAlgorithm {
         lh::heif_chroma_upsampling_algorithm_heif_chroma_upsampling_nearest_neighbor as _,
     Bilinear = lh::heif_chroma_upsampling_algorithm_heif_chroma_upsampling_bilinear as _,
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, enumn::N)]
+#[repr(C)]
+pub enum DepthRepresentationType {
+    UniformInverseZ =
+        lh::heif_depth_representation_type_heif_depth_representation_type_uniform_inverse_Z as _,
+    UniformDisparity =
+        lh::heif_depth_representation_type_heif_depth_representation_type_uniform_disparity as _,
+    UniformZ = lh::heif_depth_representation_type_heif_depth_representation_type_uniform_Z as _,
+    NonuniformDisparity =
+        lh::heif_depth_representation_type_heif_depth_representation_type_nonuniform_disparity as _,
+    /// This value is used when library `libheif` returns unknown type of depth representation.
+    Unknown,
+}
@@ -132,17 +132,20 @@ impl HeifError {
             return Ok(());
         }
 
+        // Messages from codec plugins may be in the system locale,
+        // so they are not guaranteed to be valid UTF-8.
         let message = if err.message.is_null() {
-            ""
+            String::new()
         } else {
-            let res = unsafe { CStr::from_ptr(err.message).to_str() };
-            res.unwrap_or("")
+            unsafe { CStr::from_ptr(err.message) }
+                .to_string_lossy()
+                .into_owned()
         };
 
         Err(HeifError {
             code: HeifErrorCode::n(err.code).unwrap_or(HeifErrorCode::Unknown),
             sub_code: HeifErrorSubCode::n(err.subcode).unwrap_or(HeifErrorSubCode::Unknown),
-            message: String::from(message),
+            message,
         })
     }
 }
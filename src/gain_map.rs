@@ -0,0 +1,62 @@
+/// Metadata of HDR gain map stored by Apple devices.
+///
+/// Apple stores the metadata as XMP of the gain map auxiliary image
+/// (see [`ImageHandle::gain_map()`](crate::ImageHandle::gain_map))
+/// with properties of `http://ns.apple.com/HDRGainMap/1.0/` namespace:
+///
+/// - `HDRGainMapVersion` - version of the gain map format;
+/// - `HDRGainMapHeadroom` - HDR headroom, written by newer devices only.
+///
+/// Values may be stored either as attributes of `rdf:Description`
+/// or as child elements of it. Other properties, like ISO 21496-1
+/// metadata or maker notes of Exif of the primary image, are not parsed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GainMapMetadata {
+    pub version: u32,
+    pub headroom: Option<f32>,
+}
+
+impl GainMapMetadata {
+    /// Parse gain map metadata from XMP packet.
+    ///
+    /// Returns `None` if the packet has no `HDRGainMapVersion` property
+    /// or its value is not a number.
+    pub fn from_xmp(xmp: &[u8]) -> Option<Self> {
+        let xmp = String::from_utf8_lossy(xmp);
+        let version = xmp_property(&xmp, "HDRGainMap:HDRGainMapVersion")?
+            .parse()
+            .ok()?;
+        let headroom =
+            xmp_property(&xmp, "HDRGainMap:HDRGainMapHeadroom").and_then(|v| v.parse().ok());
+        Some(Self { version, headroom })
+    }
+}
+
+/// Find value of the property with the given qualified name in XMP.
+fn xmp_property<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    // Property as an attribute: name="value"
+    let mut rest = xmp;
+    while let Some(pos) = rest.find(name) {
+        let after_name = &rest[pos + name.len()..];
+        let is_attribute = rest[..pos].ends_with(char::is_whitespace);
+        if let Some(value) = after_name.trim_start().strip_prefix('=') {
+            let value = value.trim_start();
+            if let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+                let value = &value[1..];
+                if is_attribute {
+                    if let Some(end) = value.find(quote) {
+                        return Some(value[..end].trim());
+                    }
+                }
+            }
+        }
+        rest = after_name;
+    }
+
+    // Property as an element: <name>value</name>
+    let start_tag = format!("<{}>", name);
+    let end_tag = format!("</{}>", name);
+    let start = xmp.find(&start_tag)? + start_tag.len();
+    let end = xmp[start..].find(&end_tag)? + start;
+    Some(xmp[start..end].trim())
+}
@@ -4,10 +4,11 @@ use std::ptr;
 
 use libheif_sys as lh;
 
-use crate::utils::path_to_cstring;
+use crate::utils::{cstr_to_str, path_to_cstring};
 use crate::{
-    ColorSpace, CompressionFormat, DecoderDescriptor, DecodingOptions, Encoder, EncoderDescriptor,
-    HeifError, Image, ImageHandle, Result,
+    Channel, ColorSpace, CompressionFormat, DecoderDescriptor, DecodingOptions, Encoder,
+    EncoderDescriptor, EncoderQuality, EncoderSpeed, HeifError, HeifErrorCode, HeifErrorSubCode,
+    Image, ImageHandle, Result, RgbChroma,
 };
 
 /// Guard structure used for `libheif` initialization, working with plugins,
@@ -69,6 +70,12 @@ impl LibHeif {
         [parts[0], parts[1], parts[2]]
     }
 
+    /// Version of linked `libheif` as a string, e.g. `"1.17.6"`.
+    pub fn version_string(&self) -> String {
+        let version = unsafe { lh::heif_get_version() };
+        cstr_to_str(version).unwrap_or_default().to_string()
+    }
+
     /// Load all plugins from given path of directory.
     ///
     /// Returns number of loaded plugins.
@@ -78,7 +85,7 @@ impl LibHeif {
 
     // TODO: Consider using 'momo' crate in the future
     fn _load_plugins(&self, dir_path: &Path) -> Result<usize> {
-        let dir_path = path_to_cstring(dir_path);
+        let dir_path = path_to_cstring(dir_path)?;
         let mut plugins_loaded: libc::c_int = 0;
         let err = unsafe {
             lh::heif_load_plugins(
@@ -97,6 +104,11 @@ impl LibHeif {
     ///
     /// If `color_space` is set to [`ColorSpace::Undefined`],
     /// respectively, the original colorspace is taken.
+    ///
+    /// The decoded image is already upright. The orientation tag of EXIF
+    /// metadata must not be applied to it once again, because HEIF requires
+    /// the orientation to be stored in the transformation properties
+    /// (`irot`/`imir`) and the EXIF tag is informational only.
     pub fn decode(
         &self,
         image_handle: &ImageHandle,
@@ -104,6 +116,7 @@ impl LibHeif {
         decoding_options: Option<DecodingOptions>,
     ) -> Result<Image> {
         let decoding_options_ptr = decoding_options
+            .as_ref()
             .map(|o| o.inner)
             .unwrap_or_else(ptr::null_mut);
         let mut c_image: *mut lh::heif_image = ptr::null_mut();
@@ -120,6 +133,84 @@ impl LibHeif {
         Ok(Image::from_heif_image(c_image))
     }
 
+    /// Decode an image handle and copy pixels into the given buffer.
+    ///
+    /// Row `n` of the image is copied into `dst[n * stride..]`.
+    /// `color_space` must have only one plane, i.e. it must be
+    /// [`ColorSpace::Monochrome`] or [`ColorSpace::Rgb`] with interleaved
+    /// chroma ([`RgbChroma::Rgb`], [`RgbChroma::Rgba`], etc.).
+    ///
+    /// Note: `libheif` (as of version 1.17) has no way to decode into memory
+    /// of the caller or into an existing image, `heif_decode_image()` always
+    /// allocates a new image. So the image is decoded into a temporary image
+    /// first and its rows are copied into `dst`. This method doesn't save
+    /// allocations made by `libheif`, it only allows reusing of destination
+    /// buffer between several calls.
+    pub fn decode_into(
+        &self,
+        image_handle: &ImageHandle,
+        color_space: ColorSpace,
+        decoding_options: Option<DecodingOptions>,
+        dst: &mut [u8],
+        stride: usize,
+    ) -> Result<()> {
+        let channel = match color_space {
+            ColorSpace::Monochrome => Channel::Y,
+            ColorSpace::Rgb(chroma) if chroma != RgbChroma::C444 => Channel::Interleaved,
+            _ => {
+                return Err(HeifError {
+                    code: HeifErrorCode::UsageError,
+                    sub_code: HeifErrorSubCode::InvalidParameterValue,
+                    message: format!("Color space {:?} has more than one plane", color_space),
+                })
+            }
+        };
+        let image = self.decode(image_handle, color_space, decoding_options)?;
+        let plane = image.plane(channel).ok_or(HeifError {
+            code: HeifErrorCode::DecoderPluginError,
+            sub_code: HeifErrorSubCode::NonExistingImageChannelReferenced,
+            message: format!("Decoded image has no {:?} channel", channel),
+        })?;
+
+        let row_size = plane.width as usize * plane.storage_bits_per_pixel as usize / 8;
+        let height = plane.height as usize;
+        let required_size = match height {
+            0 => 0,
+            _ => stride * (height - 1) + row_size,
+        };
+        if stride < row_size || dst.len() < required_size {
+            return Err(HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::InvalidParameterValue,
+                message: format!(
+                    "Destination buffer is too small: stride {} (required {}), size {} (required {})",
+                    stride,
+                    row_size,
+                    dst.len(),
+                    required_size
+                ),
+            });
+        }
+
+        let src_rows = plane.data.chunks(plane.stride).take(height);
+        for (dst_row, src_row) in dst.chunks_mut(stride).zip(src_rows) {
+            dst_row[..row_size].copy_from_slice(&src_row[..row_size]);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if a decoder for the given compression format
+    /// is available.
+    pub fn have_decoder_for_format(&self, format: CompressionFormat) -> bool {
+        unsafe { lh::heif_have_decoder_for_format(format as _) != 0 }
+    }
+
+    /// Returns `true` if an encoder for the given compression format
+    /// is available.
+    pub fn have_encoder_for_format(&self, format: CompressionFormat) -> bool {
+        unsafe { lh::heif_have_encoder_for_format(format as _) != 0 }
+    }
+
     /// Get a list of available decoders.
     /// You can filter the decoders by compression format.
     ///
@@ -133,6 +224,11 @@ impl LibHeif {
         let format_filter = format_filter.unwrap_or(CompressionFormat::Undefined);
         let max_count = max_count.min(libc::c_int::MAX as usize);
 
+        // With null pointer libheif returns the number of available decoders.
+        let available_count =
+            unsafe { lh::heif_get_decoder_descriptors(format_filter as _, ptr::null_mut(), 0) };
+        let max_count = max_count.min(available_count.max(0) as usize);
+
         let mut descriptors_ptr = Vec::with_capacity(max_count);
         unsafe {
             let count = lh::heif_get_decoder_descriptors(
@@ -168,7 +264,21 @@ impl LibHeif {
         let name_filter = name_filter
             .map(|s| CString::new(s).ok())
             .unwrap_or_default();
-        let name_filter_ptr = name_filter.map(|s| s.as_ptr()).unwrap_or(ptr::null());
+        let name_filter_ptr = name_filter
+            .as_ref()
+            .map(|s| s.as_ptr())
+            .unwrap_or(ptr::null());
+
+        // With null pointer libheif returns the number of available encoders.
+        let available_count = unsafe {
+            lh::heif_get_encoder_descriptors(
+                format_filter as _,
+                name_filter_ptr,
+                ptr::null_mut(),
+                0,
+            )
+        };
+        let max_count = max_count.min(available_count.max(0) as usize);
 
         let mut descriptors_ptr = Vec::with_capacity(max_count);
         unsafe {
@@ -215,4 +325,16 @@ impl LibHeif {
         let encoder = Encoder::new(unsafe { &mut *c_encoder })?;
         Ok(encoder)
     }
+
+    /// Get an AV1 encoder with the highest plugin priority
+    /// and set its lossy `quality` (0-100) and `speed` of encoding.
+    ///
+    /// See [`Encoder::set_speed`] for details about translation
+    /// of the speed into encoder specific parameter.
+    pub fn avif_encoder(&self, quality: u8, speed: EncoderSpeed) -> Result<Encoder<'_>> {
+        let mut encoder = self.encoder_for_format(CompressionFormat::Av1)?;
+        encoder.set_quality(EncoderQuality::Lossy(quality))?;
+        encoder.set_speed(speed)?;
+        Ok(encoder)
+    }
 }
@@ -6,8 +6,8 @@ use std::slice;
 use libheif_sys as lh;
 
 use crate::{
-    Channel, ColorProfileNCLX, ColorProfileRaw, ColorProfileType, ColorSpace, HeifError,
-    HeifErrorCode, HeifErrorSubCode, Result,
+    Channel, Chroma, ColorProfileNCLX, ColorProfileRaw, ColorProfileType, ColorSpace, HeifError,
+    HeifErrorCode, HeifErrorSubCode, Result, RgbChroma,
 };
 
 const MAX_IMAGE_SIZE: u32 = i32::MAX as _;
@@ -21,6 +21,37 @@ pub struct Plane<T> {
     pub storage_bits_per_pixel: u8,
 }
 
+impl<T: AsRef<[u8]>> Plane<T> {
+    /// Number of bytes with pixels in a row of the plane.
+    /// It may be less than `stride` because rows may be padded.
+    pub fn row_size(&self) -> usize {
+        self.width as usize * (self.storage_bits_per_pixel as usize / 8)
+    }
+
+    /// Get pixels of the row with index `y`, without padding bytes.
+    /// Returns `None` if `y` is out of bounds.
+    pub fn row(&self, y: u32) -> Option<&[u8]> {
+        if y >= self.height {
+            return None;
+        }
+        let start = y as usize * self.stride;
+        self.data.as_ref().get(start..start + self.row_size())
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Plane<T> {
+    /// Get mutable pixels of the row with index `y`, without padding bytes.
+    /// Returns `None` if `y` is out of bounds.
+    pub fn row_mut(&mut self, y: u32) -> Option<&mut [u8]> {
+        if y >= self.height {
+            return None;
+        }
+        let start = y as usize * self.stride;
+        let row_size = self.row_size();
+        self.data.as_mut().get_mut(start..start + row_size)
+    }
+}
+
 pub struct Planes<T> {
     pub y: Option<Plane<T>>,
     pub cb: Option<Plane<T>>,
@@ -36,6 +67,88 @@ pub struct Image {
     pub(crate) inner: *mut lh::heif_image,
 }
 
+/// Content light level information (`clli` box) of HDR image.
+///
+/// Values are in candelas per square meter.
+/// A value of 0 indicates that the value is undefined.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ContentLightLevel {
+    pub max_content_light_level: u16,
+    pub max_pic_average_light_level: u16,
+}
+
+/// Mastering display colour volume (`mdcv` box) of HDR image.
+///
+/// Values are stored in internal numeric representation
+/// of the box. Use [`MasteringDisplayColourVolume::decode()`]
+/// to get normalized values.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MasteringDisplayColourVolume {
+    pub display_primaries_x: [u16; 3],
+    pub display_primaries_y: [u16; 3],
+    pub white_point_x: u16,
+    pub white_point_y: u16,
+    pub max_display_mastering_luminance: u32,
+    pub min_display_mastering_luminance: u32,
+}
+
+/// Normalized values of [`MasteringDisplayColourVolume`].
+///
+/// Color coordinates are defined according to the CIE 1931.
+/// Luminance is in candelas per square meter.
+/// Out-of-range values are decoded to 0, indicating an undefined value.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct DecodedMasteringDisplayColourVolume {
+    pub display_primaries_x: [f32; 3],
+    pub display_primaries_y: [f32; 3],
+    pub white_point_x: f32,
+    pub white_point_y: f32,
+    pub max_display_mastering_luminance: f64,
+    pub min_display_mastering_luminance: f64,
+}
+
+impl MasteringDisplayColourVolume {
+    fn from_libheif(mdcv: &lh::heif_mastering_display_colour_volume) -> Self {
+        Self {
+            display_primaries_x: mdcv.display_primaries_x,
+            display_primaries_y: mdcv.display_primaries_y,
+            white_point_x: mdcv.white_point_x,
+            white_point_y: mdcv.white_point_y,
+            max_display_mastering_luminance: mdcv.max_display_mastering_luminance,
+            min_display_mastering_luminance: mdcv.min_display_mastering_luminance,
+        }
+    }
+
+    fn to_libheif(self) -> lh::heif_mastering_display_colour_volume {
+        lh::heif_mastering_display_colour_volume {
+            display_primaries_x: self.display_primaries_x,
+            display_primaries_y: self.display_primaries_y,
+            white_point_x: self.white_point_x,
+            white_point_y: self.white_point_y,
+            max_display_mastering_luminance: self.max_display_mastering_luminance,
+            min_display_mastering_luminance: self.min_display_mastering_luminance,
+        }
+    }
+
+    /// Convert values into normalized form.
+    pub fn decode(&self) -> Result<DecodedMasteringDisplayColourVolume> {
+        let c_mdcv = self.to_libheif();
+        let mut out = MaybeUninit::<lh::heif_decoded_mastering_display_colour_volume>::uninit();
+        let err =
+            unsafe { lh::heif_mastering_display_colour_volume_decode(&c_mdcv, out.as_mut_ptr()) };
+        HeifError::from_heif_error(err)?;
+        let out = unsafe { out.assume_init() };
+        Ok(DecodedMasteringDisplayColourVolume {
+            display_primaries_x: out.display_primaries_x,
+            display_primaries_y: out.display_primaries_y,
+            white_point_x: out.white_point_x,
+            white_point_y: out.white_point_y,
+            max_display_mastering_luminance: out.max_display_mastering_luminance,
+            min_display_mastering_luminance: out.min_display_mastering_luminance,
+        })
+    }
+}
+
 pub struct ScalingOptions {}
 
 impl Image {
@@ -120,9 +233,14 @@ impl Image {
         (value >= 0).then_some(value as _)
     }
 
-    fn plane(&self, channel: Channel) -> Option<Plane<&[u8]>> {
+    /// Get the plane of the given channel.
+    /// Returns `None` if the image has no such channel.
+    ///
+    /// Length of `data` of the returned plane is `stride * height`.
+    pub fn plane(&self, channel: Channel) -> Option<Plane<&[u8]>> {
         let mut stride: i32 = 1;
-        let data = unsafe { lh::heif_image_get_plane(self.inner, channel as _, &mut stride) };
+        let data =
+            unsafe { lh::heif_image_get_plane_readonly(self.inner, channel as _, &mut stride) };
         if data.is_null() {
             return None;
         }
@@ -143,9 +261,46 @@ impl Image {
         })
     }
 
-    fn plane_mut(&self, channel: Channel) -> Option<Plane<&mut [u8]>> {
+    /// Get the interleaved plane of an image with
+    /// `ColorSpace::Rgb(RgbChroma::Rgba)` color space.
+    ///
+    /// Use [`Plane::row`] to get pixels of the plane, because `stride`
+    /// of the plane may be greater than `width * 4`.
+    pub fn interleaved_rgba(&self) -> Result<Plane<&[u8]>> {
+        let color_space = self.color_space();
+        if color_space != Some(ColorSpace::Rgb(RgbChroma::Rgba)) {
+            return Err(HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::NonExistingImageChannelReferenced,
+                message: format!(
+                    "Image with color space {:?} has no interleaved RGBA plane",
+                    color_space
+                ),
+            });
+        }
+        self.plane(Channel::Interleaved).ok_or_else(|| HeifError {
+            code: HeifErrorCode::UsageError,
+            sub_code: HeifErrorSubCode::NonExistingImageChannelReferenced,
+            message: "Image has no interleaved plane".to_string(),
+        })
+    }
+
+    /// Get the mutable plane of the given channel.
+    /// Returns `None` if the image has no such channel.
+    ///
+    /// Length of `data` of the returned plane is `stride * height`.
+    pub fn plane_mut(&mut self, channel: Channel) -> Option<Plane<&mut [u8]>> {
+        unsafe { self.plane_mut_unchecked(channel) }
+    }
+
+    /// # Safety
+    ///
+    /// The caller must guarantee that only one mutable plane
+    /// of each channel exists at the same time.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn plane_mut_unchecked(&self, channel: Channel) -> Option<Plane<&mut [u8]>> {
         let mut stride: i32 = 1;
-        let data = unsafe { lh::heif_image_get_plane(self.inner, channel as _, &mut stride) };
+        let data = lh::heif_image_get_plane(self.inner, channel as _, &mut stride);
         if data.is_null() {
             return None;
         }
@@ -155,7 +310,7 @@ impl Image {
         let bits_per_pixel = self.bits_per_pixel(channel).unwrap_or_default();
         let storage_bits_per_pixel = self.storage_bits_per_pixel(channel).unwrap_or_default();
         let size = height as usize * stride as usize;
-        let bytes = unsafe { slice::from_raw_parts_mut(data, size) };
+        let bytes = slice::from_raw_parts_mut(data, size);
         Some(Plane {
             data: bytes,
             bits_per_pixel,
@@ -180,31 +335,113 @@ impl Image {
     }
 
     pub fn planes_mut(&mut self) -> Planes<&mut [u8]> {
-        Planes {
-            y: self.plane_mut(Channel::Y),
-            cb: self.plane_mut(Channel::Cb),
-            cr: self.plane_mut(Channel::Cr),
-            r: self.plane_mut(Channel::R),
-            g: self.plane_mut(Channel::G),
-            b: self.plane_mut(Channel::B),
-            a: self.plane_mut(Channel::Alpha),
-            interleaved: self.plane_mut(Channel::Interleaved),
+        // Every channel is requested only once.
+        unsafe {
+            Planes {
+                y: self.plane_mut_unchecked(Channel::Y),
+                cb: self.plane_mut_unchecked(Channel::Cb),
+                cr: self.plane_mut_unchecked(Channel::Cr),
+                r: self.plane_mut_unchecked(Channel::R),
+                g: self.plane_mut_unchecked(Channel::G),
+                b: self.plane_mut_unchecked(Channel::B),
+                a: self.plane_mut_unchecked(Channel::Alpha),
+                interleaved: self.plane_mut_unchecked(Channel::Interleaved),
+            }
         }
     }
 
+    /// Copy pixels from `src` buffer into the plane of the given channel.
+    ///
+    /// `src` must contain `height` rows of the plane, each row starts
+    /// at a multiple of `src_stride` bytes. Stride of the plane may differ
+    /// from `src_stride`, so data is copied row by row.
+    pub fn write_plane(&mut self, channel: Channel, src: &[u8], src_stride: usize) -> Result<()> {
+        let plane = self.plane_mut(channel).ok_or_else(|| HeifError {
+            code: HeifErrorCode::UsageError,
+            sub_code: HeifErrorSubCode::NonExistingImageChannelReferenced,
+            message: format!("Image has no plane of channel {:?}", channel),
+        })?;
+        let row_size = plane.row_size();
+        let height = plane.height as usize;
+        let required_size = match height {
+            0 => 0,
+            _ => src_stride * (height - 1) + row_size,
+        };
+        if src_stride < row_size || src.len() < required_size {
+            return Err(HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::InvalidParameterValue,
+                message: format!(
+                    "Source buffer with size {} and stride {} is too small \
+                    for plane {}x{} with row size {}",
+                    src.len(),
+                    src_stride,
+                    plane.width,
+                    plane.height,
+                    row_size
+                ),
+            });
+        }
+        if row_size == 0 {
+            return Ok(());
+        }
+        let src_rows = src.chunks(src_stride).take(height);
+        for (dst_row, src_row) in plane.data.chunks_mut(plane.stride).zip(src_rows) {
+            dst_row[..row_size].copy_from_slice(&src_row[..row_size]);
+        }
+        Ok(())
+    }
+
     pub fn has_channel(&self, channel: Channel) -> bool {
         unsafe { lh::heif_image_has_channel(self.inner, channel as _) != 0 }
     }
 
-    //    pub fn channels(&self) -> Vec<Channel> {
-    //        let mut res = Vec::from_iter();
-    //        for channel in Channel::iter() {
-    //            if self.has_channel(channel) {
-    //                res.insert(channel);
-    //            }
-    //        }
-    //        res
-    //    }
+    /// List of channels present in the image.
+    pub fn channels(&self) -> Vec<Channel> {
+        [
+            Channel::Y,
+            Channel::Cb,
+            Channel::Cr,
+            Channel::R,
+            Channel::G,
+            Channel::B,
+            Channel::Alpha,
+            Channel::Interleaved,
+        ]
+        .into_iter()
+        .filter(|&channel| self.has_channel(channel))
+        .collect()
+    }
+
+    /// Returns `true` if both images have the same size, color space,
+    /// channels and values of all pixels.
+    ///
+    /// Padding bytes at the end of rows of planes are not compared.
+    pub fn pixels_equal(&self, other: &Image) -> bool {
+        if self.width() != other.width()
+            || self.height() != other.height()
+            || self.color_space() != other.color_space()
+        {
+            return false;
+        }
+        let channels = self.channels();
+        if channels != other.channels() {
+            return false;
+        }
+        channels.into_iter().all(
+            |channel| match (self.plane(channel), other.plane(channel)) {
+                (Some(plane), Some(other_plane)) => {
+                    plane.width == other_plane.width
+                        && plane.height == other_plane.height
+                        && plane.bits_per_pixel == other_plane.bits_per_pixel
+                        && plane.storage_bits_per_pixel == other_plane.storage_bits_per_pixel
+                        && (0..plane.height).all(|y| plane.row(y) == other_plane.row(y))
+                }
+                (None, None) => true,
+                _ => false,
+            },
+        )
+    }
 
     pub fn color_space(&self) -> Option<ColorSpace> {
         unsafe {
@@ -240,6 +477,94 @@ impl Image {
         })
     }
 
+    /// Create a new image from the rectangle of this image with the top-left
+    /// corner at (`x`, `y`) and the given size.
+    ///
+    /// Pixels of all planes, color profiles and the premultiplied alpha flag
+    /// are copied into the new image.
+    ///
+    /// Chroma samples of images with subsampled chroma can't be split,
+    /// so `x` must be even for 4:2:0 and 4:2:2 images and `y` must be even
+    /// for 4:2:0 images. Otherwise, an error is returned.
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Image> {
+        let image_width = self.width();
+        let image_height = self.height();
+        let fits = |offset: u32, size: u32, max: u32| {
+            size > 0 && matches!(offset.checked_add(size), Some(end) if end <= max)
+        };
+        if !fits(x, width, image_width) || !fits(y, height, image_height) {
+            return Err(HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::InvalidParameterValue,
+                message: format!(
+                    "Crop area (x={}, y={}, width={}, height={}) \
+                    doesn't fit into image {}x{}",
+                    x, y, width, height, image_width, image_height
+                ),
+            });
+        }
+        let color_space = self.color_space().ok_or_else(|| HeifError {
+            code: HeifErrorCode::UsageError,
+            sub_code: HeifErrorSubCode::UnsupportedColorConversion,
+            message: "Image has unknown color space".to_string(),
+        })?;
+        let (sub_x, sub_y) = match color_space {
+            ColorSpace::YCbCr(Chroma::C420) => (2, 2),
+            ColorSpace::YCbCr(Chroma::C422) => (2, 1),
+            _ => (1, 1),
+        };
+        let is_aligned = |offset: u32, sub: u32| offset / sub * sub == offset;
+        if !is_aligned(x, sub_x) || !is_aligned(y, sub_y) {
+            return Err(HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::InvalidParameterValue,
+                message: format!(
+                    "Offset ({}, {}) of crop area is not aligned \
+                    to chroma subsampling {}x{} of the image",
+                    x, y, sub_x, sub_y
+                ),
+            });
+        }
+
+        let mut image = Image::new(width, height, color_space)?;
+        for channel in self.channels() {
+            let src_plane = match self.plane(channel) {
+                Some(plane) => plane,
+                None => continue,
+            };
+            let (sx, sy) = match channel {
+                Channel::Cb | Channel::Cr => (sub_x, sub_y),
+                _ => (1, 1),
+            };
+            // Size of subsampled plane is rounded up
+            let plane_width = width / sx + width % sx;
+            let plane_height = height / sy + height % sy;
+            image.create_plane(channel, plane_width, plane_height, src_plane.bits_per_pixel)?;
+            let dst_plane = image.plane_mut(channel).ok_or_else(|| HeifError {
+                code: HeifErrorCode::MemoryAllocationError,
+                sub_code: HeifErrorSubCode::Unspecified,
+                message: format!("Failed to create plane of channel {:?}", channel),
+            })?;
+            let pixel_size = src_plane.storage_bits_per_pixel as usize / 8;
+            let row_size = plane_width as usize * pixel_size;
+            let offset = (x / sx) as usize * pixel_size;
+            for row in 0..plane_height as usize {
+                let src_start = ((y / sy) as usize + row) * src_plane.stride + offset;
+                let src_row = &src_plane.data[src_start..][..row_size];
+                dst_plane.data[row * dst_plane.stride..][..row_size].copy_from_slice(src_row);
+            }
+        }
+
+        if let Some(profile) = self.color_profile_raw() {
+            image.set_color_profile_raw(&profile)?;
+        }
+        if let Some(profile) = self.color_profile_nclx() {
+            image.set_color_profile_nclx(&profile)?;
+        }
+        image.set_premultiplied_alpha(self.is_premultiplied_alpha());
+        Ok(image)
+    }
+
     /// The indicated bit_depth corresponds to the bit depth per channel.
     /// I.e. for interleaved formats like RRGGBB, the bit_depth would be, e.g., 10 bit instead
     /// of 30 bits or 3*16=48 bits.
@@ -272,6 +597,15 @@ impl Image {
         unsafe { lh::heif_image_is_premultiplied_alpha(self.inner) != 0 }
     }
 
+    /// Type of the color profile of the image.
+    ///
+    /// Returns [`crate::color_profile_types::NOT_PRESENT`] if the image has no color profile.
+    pub fn color_profile_type(&self) -> ColorProfileType {
+        let c_profile_type = unsafe { lh::heif_image_get_color_profile_type(self.inner) };
+        // `c_profile_type` on Windows will be i32, so we need to cast it to u32
+        ColorProfileType::from(c_profile_type as u32)
+    }
+
     pub fn color_profile_raw(&self) -> Option<ColorProfileRaw> {
         let size = unsafe { lh::heif_image_get_raw_color_profile_size(self.inner) };
         if size == 0 {
@@ -327,6 +661,54 @@ impl Image {
         HeifError::from_heif_error(err)
     }
 
+    /// Content light level information of the image.
+    ///
+    /// Returns `None` if the image has no such information.
+    pub fn content_light_level(&self) -> Option<ContentLightLevel> {
+        if unsafe { lh::heif_image_has_content_light_level(self.inner) } == 0 {
+            return None;
+        }
+        let mut clli = lh::heif_content_light_level {
+            max_content_light_level: 0,
+            max_pic_average_light_level: 0,
+        };
+        unsafe { lh::heif_image_get_content_light_level(self.inner, &mut clli) };
+        Some(ContentLightLevel {
+            max_content_light_level: clli.max_content_light_level,
+            max_pic_average_light_level: clli.max_pic_average_light_level,
+        })
+    }
+
+    pub fn set_content_light_level(&mut self, value: ContentLightLevel) {
+        let clli = lh::heif_content_light_level {
+            max_content_light_level: value.max_content_light_level,
+            max_pic_average_light_level: value.max_pic_average_light_level,
+        };
+        unsafe { lh::heif_image_set_content_light_level(self.inner, &clli) };
+    }
+
+    /// Mastering display colour volume of the image.
+    ///
+    /// Returns `None` if the image has no such information.
+    pub fn mastering_display_colour_volume(&self) -> Option<MasteringDisplayColourVolume> {
+        if unsafe { lh::heif_image_has_mastering_display_colour_volume(self.inner) } == 0 {
+            return None;
+        }
+        let mut mdcv = MasteringDisplayColourVolume::default().to_libheif();
+        unsafe { lh::heif_image_get_mastering_display_colour_volume(self.inner, &mut mdcv) };
+        Some(MasteringDisplayColourVolume::from_libheif(&mdcv))
+    }
+
+    pub fn set_mastering_display_colour_volume(&mut self, value: MasteringDisplayColourVolume) {
+        let mdcv = value.to_libheif();
+        unsafe { lh::heif_image_set_mastering_display_colour_volume(self.inner, &mdcv) };
+    }
+
+    /// Pixel aspect ratio as `(horizontal spacing, vertical spacing)`.
+    ///
+    /// It is `(1, 1)` for square pixels, e.g. if the file has no `pasp` box.
+    /// `libheif` doesn't provide the ratio for [`crate::ImageHandle`],
+    /// so the image has to be decoded to get it.
     pub fn pixel_aspect_ratio(&self) -> (u32, u32) {
         let mut aspect_h = 0;
         let mut aspect_v = 0;
@@ -340,6 +722,8 @@ impl Image {
         (aspect_h, aspect_v)
     }
 
+    /// Set pixel aspect ratio that is written into `pasp` box
+    /// while encoding the image.
     pub fn set_pixel_aspect_ratio(&mut self, aspect_h: u32, aspect_v: u32) {
         unsafe {
             lh::heif_image_set_pixel_aspect_ratio(self.inner, aspect_h, aspect_v);
@@ -0,0 +1,74 @@
+use ::image::RgbaImage;
+
+use crate::{
+    Channel, ColorSpace, HeifError, HeifErrorCode, HeifErrorSubCode, Image, Result, RgbChroma,
+};
+
+impl Image {
+    /// Copy pixels of the image into a new [`RgbaImage`] of `image` crate.
+    ///
+    /// The image must have the color space [`ColorSpace::Rgb`] with
+    /// 8-bit interleaved [`RgbChroma::Rgb`] or [`RgbChroma::Rgba`] channels.
+    /// Pass one of this color spaces into [`crate::LibHeif::decode()`]
+    /// to get such image.
+    pub fn to_rgba8(&self) -> Result<RgbaImage> {
+        let has_alpha = match self.color_space() {
+            Some(ColorSpace::Rgb(RgbChroma::Rgb)) => false,
+            Some(ColorSpace::Rgb(RgbChroma::Rgba)) => true,
+            color_space => {
+                return Err(HeifError {
+                    code: HeifErrorCode::UsageError,
+                    sub_code: HeifErrorSubCode::UnsupportedColorConversion,
+                    message: format!(
+                        "Can't convert image with color space {:?} into RgbaImage",
+                        color_space
+                    ),
+                })
+            }
+        };
+        let plane = self
+            .plane(Channel::Interleaved)
+            .filter(|p| p.bits_per_pixel == 8)
+            .ok_or_else(|| HeifError {
+                code: HeifErrorCode::UsageError,
+                sub_code: HeifErrorSubCode::UnsupportedBitDepth,
+                message: "Image has no 8-bit interleaved plane".to_string(),
+            })?;
+
+        let width = plane.width as usize;
+        let mut buf = Vec::with_capacity(width * plane.height as usize * 4);
+        for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+            if has_alpha {
+                buf.extend_from_slice(&row[..width * 4]);
+            } else {
+                for pixel in row[..width * 3].chunks_exact(3) {
+                    buf.extend_from_slice(pixel);
+                    buf.push(u8::MAX);
+                }
+            }
+        }
+        RgbaImage::from_raw(plane.width, plane.height, buf).ok_or_else(|| HeifError {
+            code: HeifErrorCode::MemoryAllocationError,
+            sub_code: HeifErrorSubCode::Unspecified,
+            message: "Can't create RgbaImage".to_string(),
+        })
+    }
+
+    /// Create a new image with color space [`RgbChroma::Rgba`]
+    /// from [`RgbaImage`] of `image` crate.
+    pub fn from_rgba8(img: &RgbaImage) -> Result<Image> {
+        let (width, height) = img.dimensions();
+        let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgba))?;
+        image.create_plane(Channel::Interleaved, width, height, 8)?;
+        let row_size = width as usize * 4;
+        if row_size > 0 {
+            // The plane has been created above.
+            let plane = image.plane_mut(Channel::Interleaved).unwrap();
+            let src_rows = img.as_raw().chunks_exact(row_size);
+            for (dst_row, src_row) in plane.data.chunks_mut(plane.stride).zip(src_rows) {
+                dst_row[..row_size].copy_from_slice(src_row);
+            }
+        }
+        Ok(image)
+    }
+}
@@ -1,53 +1,131 @@
 use std::ffi::CString;
+use std::hash::{Hash, Hasher};
 use std::mem::MaybeUninit;
 use std::os::raw::c_char;
 use std::ptr;
+use std::slice;
 
 use four_cc::FourCC;
 use libheif_sys as lh;
 
 use crate::utils::cstr_to_str;
 use crate::{
-    ColorProfileNCLX, ColorProfileRaw, ColorProfileType, HeifError, HeifErrorCode,
-    HeifErrorSubCode, Result,
+    color_profile_types, ColorProfileNCLX, ColorProfileRaw, ColorProfileType, ColorSpace,
+    DepthRepresentationType, GainMapMetadata, HeifError, HeifErrorCode, HeifErrorSubCode, Result,
 };
 
 /// Encoded image.
+///
+/// `ImageHandle` doesn't implement `Clone` because `libheif` has no public
+/// function to increment the reference counter of a handle. Wrap a handle
+/// into [`std::rc::Rc`] to share it. Getting a new handle by ID with
+/// [`HeifContext::image_handle()`](crate::HeifContext::image_handle)
+/// is also cheap because it doesn't parse the file again.
+///
+/// Handles of thumbnails, depth and auxiliary images don't know
+/// the image they are attached to, because `libheif` has no API
+/// to get it. Keep [`ImageHandle::item_id()`] of the master image
+/// if you need to get back to it.
 pub struct ImageHandle {
     pub(crate) inner: *mut lh::heif_image_handle,
 }
 
+/// ID of an item (image, metadata block, etc.) inside a HEIF file.
+///
+/// IDs are assigned by the file and are not indices; use methods
+/// like [`HeifContext::top_level_image_ids()`](crate::HeifContext::top_level_image_ids)
+/// or [`ImageHandle::thumbnail_ids()`] to get valid IDs.
 pub type ItemId = lh::heif_item_id;
 
-//pub struct DepthRepresentationInfo {
-//    pub version: u8,
-//    pub z_near: Option<f64>,
-//    pub z_far: Option<f64>,
-//    pub d_min: Option<f64>,
-//    pub d_max: Option<f64>,
-//    pub depth_representation_type: DepthRepresentationType,
-//    pub disparity_reference_view: u32,
-//    pub depth_nonlinear_representation_model_size: u32,
-//    pub depth_nonlinear_representation_model: *mut u8,
-//}
+/// Information about representation of values of a depth image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthRepresentationInfo {
+    pub version: u8,
+    pub z_near: Option<f64>,
+    pub z_far: Option<f64>,
+    pub d_min: Option<f64>,
+    pub d_max: Option<f64>,
+    pub depth_representation_type: DepthRepresentationType,
+    pub disparity_reference_view: u32,
+    pub depth_nonlinear_representation_model: Vec<u8>,
+}
+
+/// Metadata block attached to an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataBlock {
+    pub id: ItemId,
+    /// Item type of the block, e.g. `Exif` or `mime`.
+    pub item_type: String,
+    /// Content type of the block, e.g. `application/rdf+xml`.
+    /// It is empty for blocks that are not of type `mime`.
+    pub content_type: String,
+    /// Raw payload of the block.
+    pub data: Vec<u8>,
+}
+
+/// Filter of auxiliary images attached to an image.
+///
+/// By default, all auxiliary images are included.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AuxiliaryImagesFilter {
+    /// Omit alpha channel images.
+    pub omit_alpha: bool,
+    /// Omit depth images.
+    pub omit_depth: bool,
+}
+
+impl AuxiliaryImagesFilter {
+    fn as_c_int(&self) -> libc::c_int {
+        // Values of LIBHEIF_AUX_IMAGE_FILTER_OMIT_* constants
+        let mut res = 0;
+        if self.omit_alpha {
+            res |= 1 << 1;
+        }
+        if self.omit_depth {
+            res |= 2 << 1;
+        }
+        res
+    }
+}
 
 impl ImageHandle {
     pub(crate) fn new(handle: *mut lh::heif_image_handle) -> Self {
         ImageHandle { inner: handle }
     }
 
+    /// Raw pointer to the underlying `heif_image_handle` for direct
+    /// calls of `libheif-sys` functions that are not wrapped yet.
+    ///
+    /// The pointer is owned by this handle: don't release it and
+    /// don't use it after the handle is dropped.
+    pub fn as_raw(&self) -> *const lh::heif_image_handle {
+        self.inner
+    }
+
+    /// Mutable raw pointer to the underlying `heif_image_handle`.
+    ///
+    /// See [`ImageHandle::as_raw()`] about restrictions.
+    pub fn as_raw_mut(&mut self) -> *mut lh::heif_image_handle {
+        self.inner
+    }
+
     pub fn item_id(&self) -> ItemId {
         unsafe { lh::heif_image_handle_get_item_id(self.inner) }
     }
 
+    /// Width of the image after applying all transformations.
+    /// This doesn't decode the image.
     pub fn width(&self) -> u32 {
         unsafe { lh::heif_image_handle_get_width(self.inner) as _ }
     }
 
+    /// Height of the image after applying all transformations.
+    /// This doesn't decode the image.
     pub fn height(&self) -> u32 {
         unsafe { lh::heif_image_handle_get_height(self.inner) as _ }
     }
 
+    /// Returns `true` if the image has an alpha channel.
     pub fn has_alpha_channel(&self) -> bool {
         unsafe { lh::heif_image_handle_has_alpha_channel(self.inner) != 0 }
     }
@@ -56,18 +134,44 @@ impl ImageHandle {
         unsafe { lh::heif_image_handle_is_premultiplied_alpha(self.inner) != 0 }
     }
 
+    /// Returns `true` if this is the primary image of the file.
     pub fn is_primary(&self) -> bool {
         unsafe { lh::heif_image_handle_is_primary_image(self.inner) != 0 }
     }
 
+    /// Number of bits used for storage of luma (or monochrome) channel
+    /// of encoded image. Returns 0 if it is unknown.
     pub fn luma_bits_per_pixel(&self) -> u8 {
         unsafe { lh::heif_image_handle_get_luma_bits_per_pixel(self.inner) as _ }
     }
 
+    /// Number of bits used for storage of chroma channels
+    /// of encoded image. Returns 0 if it is unknown.
     pub fn chroma_bits_per_pixel(&self) -> u8 {
         unsafe { lh::heif_image_handle_get_chroma_bits_per_pixel(self.inner) as _ }
     }
 
+    /// Color space (with chroma) that `libheif` proposes to use for decoding
+    /// to avoid unnecessary conversions.
+    ///
+    /// Usually, this is [`ColorSpace::YCbCr`] or [`ColorSpace::Monochrome`],
+    /// but it may also be [`ColorSpace::Rgb`] for images encoded with
+    /// `matrix_coefficients=0`. Returns [`ColorSpace::Undefined`] if the file
+    /// misses relevant information to determine it without decoding.
+    pub fn preferred_decoding_colorspace(&self) -> Result<ColorSpace> {
+        let mut c_colorspace = lh::heif_colorspace_heif_colorspace_undefined;
+        let mut c_chroma = lh::heif_chroma_heif_chroma_undefined;
+        let err = unsafe {
+            lh::heif_image_handle_get_preferred_decoding_colorspace(
+                self.inner,
+                &mut c_colorspace,
+                &mut c_chroma,
+            )
+        };
+        HeifError::from_heif_error(err)?;
+        Ok(ColorSpace::from_libheif(c_colorspace, c_chroma).unwrap_or(ColorSpace::Undefined))
+    }
+
     /// Get the image width from the 'ispe' box. This is the original image size without
     /// any transformations applied to it. Do not use this unless you know exactly what
     /// you are doing.
@@ -84,14 +188,18 @@ impl ImageHandle {
 
     // Depth images
 
+    /// Returns `true` if the image has at least one depth image attached.
     pub fn has_depth_image(&self) -> bool {
         unsafe { lh::heif_image_handle_has_depth_image(self.inner) != 0 }
     }
 
+    /// Number of depth images attached to the image.
     pub fn number_of_depth_images(&self) -> i32 {
         unsafe { lh::heif_image_handle_get_number_of_depth_images(self.inner) }
     }
 
+    /// Fills `item_ids` with IDs of depth images attached to the image.
+    /// Returns the number of written IDs.
     pub fn depth_image_ids(&self, item_ids: &mut [ItemId]) -> usize {
         if item_ids.is_empty() {
             0
@@ -106,6 +214,9 @@ impl ImageHandle {
         }
     }
 
+    /// Get handle of the depth image with given ID.
+    ///
+    /// Depth image is decoded into a monochrome image.
     pub fn depth_image_handle(&self, depth_image_id: ItemId) -> Result<Self> {
         let mut out_depth_handler = MaybeUninit::<_>::uninit();
         let err = unsafe {
@@ -122,22 +233,183 @@ impl ImageHandle {
         })
     }
 
-    //    pub fn get_depth_image_representation_info(&self, depth_image_id: ItemId) {
-    //        let mut out = unsafe { mem::uninitialized() };
-    //        let res = unsafe {
-    //            heif_image_handle_get_depth_image_representation_info(
-    //                self.inner, depth_image_id,
-    //                &mut out,
-    //            )
-    //        };
-    //    }
+    /// Get handles of all depth images attached to the image.
+    pub fn depth_images(&self) -> Result<Vec<Self>> {
+        let count = self.number_of_depth_images().max(0) as usize;
+        let mut item_ids = vec![0; count];
+        let count = self.depth_image_ids(&mut item_ids);
+        item_ids.truncate(count);
+        item_ids
+            .into_iter()
+            .map(|id| self.depth_image_handle(id))
+            .collect()
+    }
+
+    /// Get information about representation of values of the depth image
+    /// attached to the image. It is required to convert depth samples
+    /// into real distances.
+    ///
+    /// If the image has several depth images, information of the first one
+    /// is returned, because `libheif` always uses it.
+    ///
+    /// Returns `None` if the information is not available.
+    ///
+    /// Note: this method must be called for handle of the main image,
+    /// not for handle of the depth image.
+    pub fn depth_representation_info(&self) -> Option<DepthRepresentationInfo> {
+        // `libheif` dereferences a null pointer if the image has no depth image
+        let mut depth_image_id: ItemId = 0;
+        if self.depth_image_ids(slice::from_mut(&mut depth_image_id)) == 0 {
+            return None;
+        }
+        let mut info_ptr: *const lh::heif_depth_representation_info = ptr::null();
+        let has_info = unsafe {
+            lh::heif_image_handle_get_depth_image_representation_info(
+                self.inner,
+                depth_image_id,
+                &mut info_ptr,
+            )
+        };
+        if has_info == 0 || info_ptr.is_null() {
+            return None;
+        }
+        let info = unsafe { &*info_ptr };
+        let flag_value = |flag: u8, value: f64| (flag != 0).then_some(value);
+        let model_size = info.depth_nonlinear_representation_model_size as usize;
+        let depth_nonlinear_representation_model = if model_size == 0
+            || info.depth_nonlinear_representation_model.is_null()
+        {
+            Vec::new()
+        } else {
+            unsafe { slice::from_raw_parts(info.depth_nonlinear_representation_model, model_size) }
+                .to_vec()
+        };
+        let res = DepthRepresentationInfo {
+            version: info.version,
+            z_near: flag_value(info.has_z_near, info.z_near),
+            z_far: flag_value(info.has_z_far, info.z_far),
+            d_min: flag_value(info.has_d_min, info.d_min),
+            d_max: flag_value(info.has_d_max, info.d_max),
+            depth_representation_type: DepthRepresentationType::n(info.depth_representation_type)
+                .unwrap_or(DepthRepresentationType::Unknown),
+            disparity_reference_view: info.disparity_reference_view,
+            depth_nonlinear_representation_model,
+        };
+        unsafe { lh::heif_depth_representation_info_free(info_ptr) };
+        Some(res)
+    }
+
+    // Auxiliary images
+
+    /// Number of auxiliary images (e.g. alpha channel, depth map or gain map)
+    /// attached to the image.
+    pub fn number_of_auxiliary_images(&self, filter: AuxiliaryImagesFilter) -> usize {
+        unsafe {
+            lh::heif_image_handle_get_number_of_auxiliary_images(self.inner, filter.as_c_int()) as _
+        }
+    }
+
+    /// Fills `item_ids` with IDs of auxiliary images attached to the image.
+    /// Returns the number of written IDs.
+    pub fn auxiliary_image_ids(
+        &self,
+        item_ids: &mut [ItemId],
+        filter: AuxiliaryImagesFilter,
+    ) -> usize {
+        if item_ids.is_empty() {
+            0
+        } else {
+            unsafe {
+                lh::heif_image_handle_get_list_of_auxiliary_image_IDs(
+                    self.inner,
+                    filter.as_c_int(),
+                    item_ids.as_mut_ptr(),
+                    item_ids.len() as _,
+                ) as usize
+            }
+        }
+    }
+
+    /// Get handle of the auxiliary image with given ID.
+    pub fn auxiliary_image_handle(&self, auxiliary_id: ItemId) -> Result<Self> {
+        let mut out_handle: *mut lh::heif_image_handle = ptr::null_mut();
+        let err = unsafe {
+            lh::heif_image_handle_get_auxiliary_image_handle(
+                self.inner,
+                auxiliary_id,
+                &mut out_handle,
+            )
+        };
+        HeifError::from_heif_error(err)?;
+        Ok(ImageHandle::new(out_handle))
+    }
+
+    /// Get handles of all auxiliary images attached to the image.
+    pub fn auxiliary_images(&self, filter: AuxiliaryImagesFilter) -> Result<Vec<Self>> {
+        let mut item_ids = vec![0; self.number_of_auxiliary_images(filter)];
+        let count = self.auxiliary_image_ids(&mut item_ids, filter);
+        item_ids.truncate(count);
+        item_ids
+            .into_iter()
+            .map(|id| self.auxiliary_image_handle(id))
+            .collect()
+    }
+
+    /// Type of auxiliary image (URN), e.g.
+    /// `urn:mpeg:mpegB:cicp:systems:auxiliary:alpha`.
+    ///
+    /// Returns `None` if the image is not an auxiliary image.
+    pub fn auxiliary_type(&self) -> Option<String> {
+        let mut c_type: *const c_char = ptr::null();
+        let err = unsafe { lh::heif_image_handle_get_auxiliary_type(self.inner, &mut c_type) };
+        if err.code != 0 || c_type.is_null() {
+            return None;
+        }
+        let res = cstr_to_str(c_type)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+        unsafe { lh::heif_image_handle_release_auxiliary_type(self.inner, &mut c_type) };
+        res
+    }
+
+    /// Get handle of the HDR gain map stored by Apple devices as
+    /// an auxiliary image with type `urn:com:apple:photo:2020:aux:hdrgainmap`.
+    ///
+    /// Returns `None` if the image has no such gain map.
+    pub fn gain_map(&self) -> Result<Option<Self>> {
+        const APPLE_GAIN_MAP_URN: &str = "urn:com:apple:photo:2020:aux:hdrgainmap";
+        let filter = AuxiliaryImagesFilter {
+            omit_alpha: true,
+            omit_depth: true,
+        };
+        for handle in self.auxiliary_images(filter)? {
+            if handle.auxiliary_type().as_deref() == Some(APPLE_GAIN_MAP_URN) {
+                return Ok(Some(handle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get metadata of the gain map from XMP of this handle.
+    /// It must be called for the handle returned by [`ImageHandle::gain_map()`].
+    ///
+    /// Returns `Ok(None)` if the image has no XMP or the XMP contains
+    /// no gain map metadata. Raw XMP is available with
+    /// [`ImageHandle::xmp_metadata()`].
+    pub fn gain_map_metadata(&self) -> Result<Option<GainMapMetadata>> {
+        let xmp = self.xmp_metadata()?;
+        Ok(xmp.and_then(|xmp| GainMapMetadata::from_xmp(&xmp)))
+    }
 
     // Thumbnails
 
+    /// Number of thumbnails attached to the image.
     pub fn number_of_thumbnails(&self) -> usize {
         unsafe { lh::heif_image_handle_get_number_of_thumbnails(self.inner) as _ }
     }
 
+    /// Fills `item_ids` with IDs of thumbnails attached to the image.
+    /// Returns the number of written IDs.
     pub fn thumbnail_ids(&self, item_ids: &mut [ItemId]) -> usize {
         if item_ids.is_empty() {
             0
@@ -152,6 +424,7 @@ impl ImageHandle {
         }
     }
 
+    /// Get handle of the thumbnail with given ID.
     pub fn thumbnail(&self, thumbnail_id: ItemId) -> Result<Self> {
         let mut out_thumbnail_handler = MaybeUninit::<_>::uninit();
         let err = unsafe {
@@ -168,6 +441,14 @@ impl ImageHandle {
         })
     }
 
+    /// Get handles of all thumbnails attached to the image.
+    pub fn thumbnails(&self) -> Result<Vec<Self>> {
+        let mut item_ids = vec![0; self.number_of_thumbnails()];
+        let count = self.thumbnail_ids(&mut item_ids);
+        item_ids.truncate(count);
+        item_ids.into_iter().map(|id| self.thumbnail(id)).collect()
+    }
+
     // Metadata
 
     fn convert_type_filter<T>(type_filter: T) -> Option<CString>
@@ -253,6 +534,118 @@ impl ImageHandle {
         Ok(result)
     }
 
+    /// Get all metadata blocks attached to the image.
+    pub fn metadata_blocks(&self) -> Result<Vec<MetadataBlock>> {
+        let count = self.number_of_metadata_blocks(0);
+        if count <= 0 {
+            return Ok(Vec::new());
+        }
+        let mut item_ids: Vec<ItemId> = vec![0; count as usize];
+        let count = self.metadata_block_ids(&mut item_ids, 0);
+        item_ids.truncate(count);
+        item_ids
+            .into_iter()
+            .map(|id| {
+                let data = if self.metadata_size(id) == 0 {
+                    Vec::new()
+                } else {
+                    self.metadata(id)?
+                };
+                Ok(MetadataBlock {
+                    id,
+                    item_type: self.metadata_type(id).unwrap_or_default().to_string(),
+                    content_type: self
+                        .metadata_content_type(id)
+                        .unwrap_or_default()
+                        .to_string(),
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    /// Get EXIF metadata of the image.
+    ///
+    /// Returns `Ok(None)` if the image has no EXIF metadata block.
+    /// In HEIF file EXIF data is prefixed by 4-byte offset to TIFF header.
+    /// This method skips the prefix, so returned bytes start
+    /// with TIFF header (`MM\0*` or `II*\0`).
+    pub fn exif_metadata(&self) -> Result<Option<Vec<u8>>> {
+        let mut item_ids: [ItemId; 1] = [0];
+        if self.metadata_block_ids(&mut item_ids, b"Exif") == 0 {
+            return Ok(None);
+        }
+        let mut exif = self.metadata(item_ids[0])?;
+        let tiff_header_pos = exif
+            .get(0..4)
+            .map(|prefix| u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]))
+            .and_then(|offset| (offset as usize).checked_add(4))
+            .filter(|&pos| pos <= exif.len());
+        match tiff_header_pos {
+            Some(pos) => {
+                exif.drain(0..pos);
+                Ok(Some(exif))
+            }
+            None => Err(HeifError {
+                code: HeifErrorCode::InvalidInput,
+                sub_code: HeifErrorSubCode::EndOfData,
+                message: "Invalid offset of TIFF header in EXIF metadata".to_string(),
+            }),
+        }
+    }
+
+    /// Get XMP metadata of the image.
+    ///
+    /// Returns `Ok(None)` if the image has no metadata block
+    /// with content type `application/rdf+xml`.
+    pub fn xmp_metadata(&self) -> Result<Option<Vec<u8>>> {
+        let count = self.number_of_metadata_blocks(b"mime");
+        if count <= 0 {
+            return Ok(None);
+        }
+        let mut item_ids: Vec<ItemId> = vec![0; count as usize];
+        let count = self.metadata_block_ids(&mut item_ids, b"mime");
+        for &item_id in item_ids[..count].iter() {
+            if self.metadata_content_type(item_id) == Some("application/rdf+xml") {
+                return self.metadata(item_id).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Type of the color profile of the image.
+    ///
+    /// Returns [`color_profile_types::NOT_PRESENT`] if the image has no color profile.
+    pub fn color_profile_type(&self) -> ColorProfileType {
+        let c_profile_type = unsafe { lh::heif_image_handle_get_color_profile_type(self.inner) };
+        // `c_profile_type` on Windows will be i32, so we need to cast it to u32
+        ColorProfileType::from(c_profile_type as u32)
+    }
+
+    /// Get ICC color profile of the image.
+    ///
+    /// Returns `Ok(None)` if the image has no color profile or
+    /// it has a profile of a type other than ICC (e.g. NCLX).
+    pub fn icc_profile(&self) -> Result<Option<Vec<u8>>> {
+        let profile_type = self.color_profile_type();
+        if profile_type != color_profile_types::PROF && profile_type != color_profile_types::R_ICC {
+            return Ok(None);
+        }
+        let size = unsafe { lh::heif_image_handle_get_raw_color_profile_size(self.inner) };
+        if size == 0 {
+            return Ok(None);
+        }
+        let mut result: Vec<u8> = Vec::with_capacity(size);
+        let err = unsafe {
+            lh::heif_image_handle_get_raw_color_profile(self.inner, result.as_mut_ptr() as _)
+        };
+        HeifError::from_heif_error(err)?;
+        unsafe {
+            result.set_len(size);
+        }
+        Ok(Some(result))
+    }
+
     pub fn color_profile_raw(&self) -> Option<ColorProfileRaw> {
         let size = unsafe { lh::heif_image_handle_get_raw_color_profile_size(self.inner) };
         if size == 0 {
@@ -304,3 +697,21 @@ impl Drop for ImageHandle {
         unsafe { lh::heif_image_handle_release(self.inner) };
     }
 }
+
+/// Handles are equal if they have the same [`ImageHandle::item_id()`].
+///
+/// IDs are unique only inside one file, so don't compare
+/// handles received from different contexts.
+impl PartialEq for ImageHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.item_id() == other.item_id()
+    }
+}
+
+impl Eq for ImageHandle {}
+
+impl Hash for ImageHandle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.item_id().hash(state);
+    }
+}
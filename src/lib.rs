@@ -1,25 +1,37 @@
 #![doc = include_str!("../README.md")]
 
 pub use color_profile::*;
-pub use context::HeifContext;
+pub use context::{ContextStats, HeifContext};
 pub use decoder::*;
+pub use easy::{decode_first_image, encode_rgb};
 pub use encoder::*;
 pub use enums::*;
 pub use errors::{HeifError, HeifErrorCode, HeifErrorSubCode, Result};
+pub use gain_map::GainMapMetadata;
 pub use heif::*;
 pub use image::*;
-pub use image_handle::{ImageHandle, ItemId};
+pub use image_handle::{
+    AuxiliaryImagesFilter, DepthRepresentationInfo, ImageHandle, ItemId, MetadataBlock,
+};
 pub use reader::{Reader, StreamReader};
-pub use utils::check_file_type;
+pub use utils::{check_file_type, file_compression_format, file_mime_type};
+pub use writer::Writer;
 
 mod color_profile;
 mod context;
 mod decoder;
+mod easy;
 mod encoder;
 mod enums;
 mod errors;
+mod gain_map;
 mod heif;
 mod image;
+#[cfg(feature = "image")]
+mod image_crate;
 mod image_handle;
 mod reader;
+#[cfg(feature = "tokio")]
+mod tokio_reader;
 mod utils;
+mod writer;
@@ -49,7 +49,18 @@ where
     }
 
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.stream.read(buf)
+        // `libheif` expects that the whole buffer will be filled,
+        // so we have to continue reading after a partial read.
+        let mut total = 0;
+        while total < buf.len() {
+            match self.stream.read(&mut buf[total..]) {
+                Ok(0) => break,
+                Ok(size) => total += size,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(total)
     }
 
     fn seek(&mut self, position: u64) -> io::Result<u64> {
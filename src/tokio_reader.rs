@@ -0,0 +1,26 @@
+use ::tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{HeifContext, HeifError, HeifErrorCode, HeifErrorSubCode, Result};
+
+impl HeifContext<'static> {
+    /// Create a new context from an async source.
+    ///
+    /// `libheif` reads data synchronously, so this method
+    /// reads the whole source into memory first and then parses it
+    /// with [`HeifContext::read_from_bytes_owned()`].
+    pub async fn read_from_async<R>(mut reader: R) -> Result<HeifContext<'static>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|err| HeifError {
+                code: HeifErrorCode::InvalidInput,
+                sub_code: HeifErrorSubCode::Unspecified,
+                message: format!("Failed to read data: {}", err),
+            })?;
+        Self::read_from_bytes_owned(bytes)
+    }
+}
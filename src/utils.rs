@@ -4,7 +4,7 @@ use std::path::Path;
 
 use libheif_sys as lh;
 
-use crate::{FileTypeResult, HeifError, HeifErrorCode, HeifErrorSubCode};
+use crate::{CompressionFormat, FileTypeResult, HeifError, HeifErrorCode, HeifErrorSubCode};
 
 #[inline]
 pub(crate) fn cstr_to_str<'a>(c_str: *const c_char) -> Option<&'a str> {
@@ -27,30 +27,65 @@ pub(crate) fn str_to_cstring(s: &str, name: &str) -> Result<CString, HeifError>
     })
 }
 
-pub(crate) fn path_to_cstring(path: &Path) -> CString {
+pub(crate) fn path_to_cstring(path: &Path) -> Result<CString, HeifError> {
     #[cfg(unix)]
-    {
+    let bytes = {
         use std::os::unix::ffi::OsStrExt;
-        CString::new(path.as_os_str().as_bytes()).unwrap_or_default()
-    }
+        path.as_os_str().as_bytes()
+    };
 
-    #[cfg(windows)]
-    {
-        use std::os::windows::ffi::OsStrExt;
-        let mut buf = Vec::new();
-        buf.extend(
-            path.as_os_str()
-                .encode_wide()
-                .chain(Some(0))
-                .flat_map(|b| b.to_ne_bytes()),
-        );
-        CString::new(buf).unwrap_or_default()
-    }
+    // `libheif` expects paths in UTF-8 on other platforms.
+    #[cfg(not(unix))]
+    let bytes = path
+        .to_str()
+        .ok_or_else(|| HeifError {
+            code: HeifErrorCode::UsageError,
+            sub_code: HeifErrorSubCode::InvalidParameterValue,
+            message: format!("Path is not valid UTF-8: {}", path.display()),
+        })?
+        .as_bytes();
+
+    CString::new(bytes).map_err(|e| HeifError {
+        code: HeifErrorCode::UsageError,
+        sub_code: HeifErrorSubCode::InvalidParameterValue,
+        message: format!("Invalid path: {}", e),
+    })
 }
 
 /// Check file type by it first bytes.
 /// Input data should be at least 12 bytes.
 pub fn check_file_type(data: &[u8]) -> FileTypeResult {
-    let res = unsafe { lh::heif_check_filetype(data.as_ptr(), data.len() as _) };
+    let len = data.len().min(libc::c_int::MAX as usize);
+    let res = unsafe { lh::heif_check_filetype(data.as_ptr(), len as _) };
     FileTypeResult::n(res).unwrap_or(FileTypeResult::No)
 }
+
+/// Get MIME type of file by it first bytes, e.g. `image/heic`,
+/// `image/heif`, `image/avif` or their `-sequence` variants.
+/// Returns `None` if the format could not be detected.
+///
+/// Input data should be at least 12 bytes. You may also provide
+/// more data to increase detection accuracy.
+///
+/// Note that JPEG and PNG images are also detected (`image/jpeg`
+/// and `image/png`), but they can't be decoded by `libheif`.
+pub fn file_mime_type(data: &[u8]) -> Option<&'static str> {
+    let len = data.len().min(libc::c_int::MAX as usize);
+    let mime_type = unsafe { lh::heif_get_file_mime_type(data.as_ptr(), len as _) };
+    cstr_to_str(mime_type).filter(|s| !s.is_empty())
+}
+
+/// Detect compression format of images in the file by its first bytes.
+///
+/// The format is detected by the brand of the file:
+/// [`CompressionFormat::Hevc`] for HEIC files and
+/// [`CompressionFormat::Av1`] for AVIF files (including sequences).
+/// Returns [`CompressionFormat::Undefined`] for other files,
+/// e.g. for files with generic `mif1` brand.
+pub fn file_compression_format(data: &[u8]) -> CompressionFormat {
+    match file_mime_type(data) {
+        Some("image/heic" | "image/heic-sequence") => CompressionFormat::Hevc,
+        Some("image/avif" | "image/avif-sequence") => CompressionFormat::Av1,
+        _ => CompressionFormat::Undefined,
+    }
+}
@@ -0,0 +1,101 @@
+use std::io;
+use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+
+use libheif_sys as lh;
+
+use crate::{HeifError, HeifErrorCode, HeifErrorSubCode, Result};
+
+/// Destination of encoded data for
+/// [`HeifContext::write_to_writer()`](crate::HeifContext::write_to_writer).
+///
+/// `libheif` passes encoded data into [`Writer::write()`] only while
+/// writing of the context is in progress, so the writer is borrowed
+/// for the time of the call and not owned by the context.
+pub trait Writer {
+    /// Write all bytes from the specified buffer into a destination.
+    fn write(&mut self, data: &[u8]) -> Result<()>;
+}
+
+/// Adapter to use any [`io::Write`] as [`Writer`].
+pub(crate) struct IoWriter<'a, W: io::Write> {
+    inner: &'a mut W,
+}
+
+impl<'a, W: io::Write> IoWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self { inner }
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        self.inner.flush().map_err(io_error)
+    }
+}
+
+impl<'a, W: io::Write> Writer for IoWriter<'a, W> {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.inner.write_all(data).map_err(io_error)
+    }
+}
+
+fn io_error(err: io::Error) -> HeifError {
+    HeifError {
+        code: HeifErrorCode::EncodingError,
+        sub_code: HeifErrorSubCode::CannotWriteOutputData,
+        message: format!("Failed to write data: {}", err),
+    }
+}
+
+/// State passed into libheif as `user_data` of the write callback.
+pub(crate) struct WriterState<'a> {
+    writer: &'a mut dyn Writer,
+    /// Error returned by the writer. It is returned to the caller
+    /// instead of the generic error received from libheif.
+    pub(crate) error: Option<HeifError>,
+}
+
+impl<'a> WriterState<'a> {
+    pub(crate) fn new(writer: &'a mut dyn Writer) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+}
+
+unsafe extern "C" fn write(
+    _ctx: *mut lh::heif_context,
+    data: *const c_void,
+    size: usize,
+    user_data: *mut c_void,
+) -> lh::heif_error {
+    let state = &mut *(user_data as *mut WriterState);
+    let buf = if size == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data as *const u8, size)
+    };
+    match state.writer.write(buf) {
+        Ok(_) => lh::heif_error {
+            code: lh::heif_error_code_heif_error_Ok,
+            subcode: lh::heif_suberror_code_heif_suberror_Unspecified,
+            message: ptr::null(),
+        },
+        Err(err) => {
+            state.error = Some(err);
+            lh::heif_error {
+                code: lh::heif_error_code_heif_error_Encoding_error,
+                subcode: lh::heif_suberror_code_heif_suberror_Cannot_write_output_data,
+                message: c"Writer failed to write output data".as_ptr(),
+            }
+        }
+    }
+}
+
+pub(crate) fn heif_writer() -> lh::heif_writer {
+    lh::heif_writer {
+        writer_api_version: 1,
+        write: Some(write),
+    }
+}
@@ -0,0 +1,20 @@
+use libheif_rs::{decode_first_image, encode_rgb, Channel, CompressionFormat, Result};
+
+#[test]
+fn encode_and_decode_rgb() -> Result<()> {
+    let (width, height) = (64u32, 48u32);
+    let rgb: Vec<u8> = (0..width * height * 3).map(|i| (i % 251) as u8).collect();
+    let buf = encode_rgb(width, height, &rgb, CompressionFormat::Av1, 90)?;
+    assert!(!buf.is_empty());
+
+    let image = decode_first_image(&buf)?;
+    assert_eq!(image.width(), width);
+    assert_eq!(image.height(), height);
+    let plane = image.plane(Channel::Interleaved).unwrap();
+    assert_eq!(plane.bits_per_pixel, 8);
+    assert_eq!(plane.storage_bits_per_pixel, 24);
+
+    // The buffer is too small
+    assert!(encode_rgb(width, height, &rgb[1..], CompressionFormat::Av1, 90).is_err());
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::error::Error;
+
+use libheif_rs::{HeifContext, HeifError, HeifErrorCode, HeifErrorSubCode};
+
+fn read_invalid_data() -> Result<(), Box<dyn Error + Send + Sync>> {
+    HeifContext::read_from_bytes(b"invalid data")?;
+    Ok(())
+}
+
+#[test]
+fn heif_error_into_boxed_error() {
+    let err = read_invalid_data().unwrap_err();
+    assert!(err.source().is_none());
+
+    let heif_err = err.downcast_ref::<HeifError>().unwrap();
+    assert!(matches!(heif_err.code, HeifErrorCode::InvalidInput));
+    let text = err.to_string();
+    assert!(text.starts_with("InvalidInput("));
+    assert!(text.ends_with(&heif_err.message));
+}
+
+#[test]
+fn heif_error_with_invalid_utf8_message() {
+    let message = b"Fehler \xe4\xfc\0";
+    let lh_err = libheif_sys::heif_error {
+        code: libheif_sys::heif_error_code_heif_error_Encoder_plugin_error,
+        subcode: libheif_sys::heif_suberror_code_heif_suberror_Encoder_encoding,
+        message: message.as_ptr() as _,
+    };
+    let err = HeifError::from_heif_error(lh_err).unwrap_err();
+    assert!(matches!(err.code, HeifErrorCode::EncoderPluginError));
+    assert!(matches!(err.sub_code, HeifErrorSubCode::EncoderEncoding));
+    assert_eq!(err.message, "Fehler \u{fffd}\u{fffd}");
+}
+
+#[test]
+fn heif_error_with_null_message() {
+    let lh_err = libheif_sys::heif_error {
+        code: libheif_sys::heif_error_code_heif_error_Usage_error,
+        subcode: libheif_sys::heif_suberror_code_heif_suberror_Unspecified,
+        message: std::ptr::null(),
+    };
+    let err = HeifError::from_heif_error(lh_err).unwrap_err();
+    assert!(matches!(err.code, HeifErrorCode::UsageError));
+    assert!(err.message.is_empty());
+}
+
+#[test]
+fn display_of_heif_error_names_sub_code() {
+    let lh_err = libheif_sys::heif_error {
+        code: libheif_sys::heif_error_code_heif_error_Unsupported_feature,
+        subcode: libheif_sys::heif_suberror_code_heif_suberror_Unsupported_image_type,
+        message: c"Unsupported image type".as_ptr(),
+    };
+    let err = HeifError::from_heif_error(lh_err).unwrap_err();
+    assert!(matches!(
+        err.sub_code,
+        HeifErrorSubCode::UnsupportedImageType
+    ));
+    assert_eq!(
+        err.to_string(),
+        "UnsupportedFeature(UnsupportedImageType) Unsupported image type"
+    );
+}
@@ -0,0 +1,49 @@
+use libheif_rs::{GainMapMetadata, HeifContext, Result};
+
+#[test]
+fn gain_map_metadata_from_xmp_attributes() {
+    let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:HDRGainMap="http://ns.apple.com/HDRGainMap/1.0/"
+    HDRGainMap:HDRGainMapVersion="65536"
+    HDRGainMap:HDRGainMapHeadroom="2.5"/>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+    let metadata = GainMapMetadata::from_xmp(xmp).unwrap();
+    assert_eq!(metadata.version, 65536);
+    assert_eq!(metadata.headroom, Some(2.5));
+}
+
+#[test]
+fn gain_map_metadata_from_xmp_elements() {
+    let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:HDRGainMap="http://ns.apple.com/HDRGainMap/1.0/">
+   <HDRGainMap:HDRGainMapVersion>131072</HDRGainMap:HDRGainMapVersion>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+    let metadata = GainMapMetadata::from_xmp(xmp).unwrap();
+    assert_eq!(metadata.version, 131072);
+    assert_eq!(metadata.headroom, None);
+}
+
+#[test]
+fn xmp_without_gain_map_metadata() {
+    assert!(GainMapMetadata::from_xmp(b"").is_none());
+    let xmp = br#"<rdf:Description xmp:CreatorTool="HDRGainMap:HDRGainMapVersion='1'"/>"#;
+    assert!(GainMapMetadata::from_xmp(xmp).is_none());
+    let xmp = br#"<rdf:Description HDRGainMap:HDRGainMapVersion="unknown"/>"#;
+    assert!(GainMapMetadata::from_xmp(xmp).is_none());
+}
+
+#[test]
+fn image_without_gain_map_metadata() -> Result<()> {
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+    assert!(handle.gain_map()?.is_none());
+    assert!(handle.gain_map_metadata()?.is_none());
+    Ok(())
+}
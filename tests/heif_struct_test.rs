@@ -1,4 +1,4 @@
-use libheif_rs::LibHeif;
+use libheif_rs::{CompressionFormat, EncoderParameterValue, EncoderQuality, EncoderSpeed, LibHeif};
 use std::collections::HashSet;
 
 #[test]
@@ -7,6 +7,11 @@ fn get_version() {
     let version = lib_heif.version();
     assert!(version[0] >= 1);
     assert!(version[1] >= 14);
+    let [major, minor, maintenance] = version;
+    assert_eq!(
+        lib_heif.version_string(),
+        format!("{}.{}.{}", major, minor, maintenance)
+    );
 }
 
 #[test]
@@ -16,6 +21,20 @@ fn get_encoder_descriptors() {
     assert!(descriptors.len() >= 2);
 }
 
+#[test]
+fn get_encoder_descriptors_by_name() {
+    let lib_heif = LibHeif::new();
+    let descriptors = lib_heif.encoder_descriptors(100, None, None);
+    for descriptor in descriptors.iter() {
+        let found = lib_heif.encoder_descriptors(1, None, Some(descriptor.id()));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id(), descriptor.id());
+    }
+    assert!(lib_heif
+        .encoder_descriptors(1, None, Some("unknown"))
+        .is_empty());
+}
+
 #[test]
 fn get_encoder() {
     let lib_heif = LibHeif::new();
@@ -38,9 +57,55 @@ fn get_encoder_for_format() {
     }
 }
 
+#[test]
+fn get_avif_encoder() {
+    let lib_heif = LibHeif::new();
+    if !lib_heif.have_encoder_for_format(CompressionFormat::Av1) {
+        return;
+    }
+    let encoder = lib_heif.avif_encoder(60, EncoderSpeed::Fast).unwrap();
+    assert_eq!(
+        encoder.parameter("quality").unwrap(),
+        Some(EncoderParameterValue::Int(60))
+    );
+    assert!(lib_heif.avif_encoder(101, EncoderSpeed::Fast).is_err());
+}
+
 #[test]
 fn get_decoder_descriptors() {
     let lib_heif = LibHeif::new();
     let descriptors = lib_heif.decoder_descriptors(100, None);
     assert!(descriptors.len() >= 2);
 }
+
+#[test]
+fn have_encoder_and_decoder_for_format() {
+    let lib_heif = LibHeif::new();
+    for descriptor in lib_heif.encoder_descriptors(100, None, None) {
+        assert!(lib_heif.have_encoder_for_format(descriptor.compression_format()));
+    }
+    for format in [
+        CompressionFormat::Hevc,
+        CompressionFormat::Av1,
+        CompressionFormat::Jpeg,
+    ] {
+        assert_eq!(
+            lib_heif.have_decoder_for_format(format),
+            !lib_heif.decoder_descriptors(1, Some(format)).is_empty()
+        );
+    }
+    assert!(!lib_heif.have_encoder_for_format(CompressionFormat::Undefined));
+    assert!(!lib_heif.have_decoder_for_format(CompressionFormat::Undefined));
+}
+
+#[test]
+fn lossless_support_of_encoders() {
+    let lib_heif = LibHeif::new();
+    for descriptor in lib_heif.encoder_descriptors(100, None, None) {
+        let mut encoder = lib_heif.encoder(descriptor).unwrap();
+        let has_parameter = encoder.parameters_names().iter().any(|n| n == "lossless");
+        assert_eq!(encoder.supports_lossless(), has_parameter);
+        let res = encoder.set_quality(EncoderQuality::LossLess);
+        assert_eq!(res.is_ok(), encoder.supports_lossless());
+    }
+}
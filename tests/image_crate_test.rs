@@ -0,0 +1,48 @@
+#![cfg(feature = "image")]
+
+use image::{Rgba, RgbaImage};
+use libheif_rs::{
+    Channel, ColorSpace, CompressionFormat, EncoderQuality, HeifContext, Image, LibHeif, Result,
+    RgbChroma,
+};
+
+#[test]
+fn decode_into_rgba_image() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)?;
+    let rgba_image = image.to_rgba8()?;
+    assert_eq!(rgba_image.dimensions(), (1652, 1791));
+    assert!(rgba_image.pixels().all(|p| p[3] == 255));
+
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+    assert!(image.to_rgba8().is_err());
+    Ok(())
+}
+
+#[test]
+fn rgba_image_round_trip() -> Result<()> {
+    let rgba_image = RgbaImage::from_fn(33, 17, |x, y| Rgba([x as u8, y as u8, 100, 200]));
+    let image = Image::from_rgba8(&rgba_image)?;
+    assert_eq!(image.color_space(), Some(ColorSpace::Rgb(RgbChroma::Rgba)));
+    assert_eq!(image.width(), 33);
+    assert_eq!(image.height(), 17);
+    assert!(image.has_channel(Channel::Interleaved));
+    assert_eq!(image.to_rgba8()?, rgba_image);
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    encoder.set_quality(EncoderQuality::LossLess)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)?;
+    let decoded = image.to_rgba8()?;
+    assert_eq!(decoded.dimensions(), (33, 17));
+    Ok(())
+}
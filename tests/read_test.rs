@@ -1,13 +1,16 @@
+use std::cell::RefCell;
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::rc::Rc;
 
 use exif::parse_exif;
 
 use libheif_rs::{
-    check_file_type, color_profile_types, Chroma, ChromaDownsamplingAlgorithm,
-    ChromaUpsamplingAlgorithm, ColorPrimaries, ColorProfile, ColorSpace, CompressionFormat,
-    DecodingOptions, FileTypeResult, HeifContext, ImageHandle, ItemId, LibHeif, MatrixCoefficients,
-    Result, RgbChroma, StreamReader, TransferCharacteristics,
+    check_file_type, color_profile_types, file_compression_format, file_mime_type, Channel, Chroma,
+    ChromaDownsamplingAlgorithm, ChromaUpsamplingAlgorithm, ColorPrimaries, ColorProfile,
+    ColorSpace, CompressionFormat, DecodingOptions, FileTypeResult, HeifContext, ImageHandle,
+    ItemId, LibHeif, MatrixCoefficients, ProgressEvent, Result, RgbChroma, StreamReader,
+    TransferCharacteristics,
 };
 
 #[test]
@@ -20,6 +23,21 @@ fn read_from_file() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn read_from_file_by_path() -> Result<()> {
+    let path = std::path::PathBuf::from("./data").join("test.heif");
+    let ctx = HeifContext::read_from_file(&path)?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.width(), 1652);
+    Ok(())
+}
+
+#[test]
+fn read_from_file_with_nul_in_name() {
+    let res = HeifContext::read_from_file("a\0b.heic");
+    assert!(res.is_err());
+}
+
 #[test]
 fn read_from_memory() -> Result<()> {
     let mut buffer = Vec::new();
@@ -35,6 +53,20 @@ fn read_from_memory() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn read_from_owned_bytes() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let data = std::fs::read("./data/test.heif").unwrap();
+    let ctx = HeifContext::read_from_bytes_owned(data)?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.width(), 1652);
+    assert_eq!(handle.height(), 1791);
+
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+    assert_eq!(image.width(), 1652);
+    Ok(())
+}
+
 #[test]
 fn read_from_reader() -> Result<()> {
     let lib_heif = LibHeif::new();
@@ -58,6 +90,29 @@ fn read_from_reader() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn read_from_io() -> Result<()> {
+    let file = File::open("./data/test.heif").unwrap();
+    let ctx = HeifContext::read_from_io(BufReader::new(file))?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.width(), 1652);
+    assert_eq!(handle.height(), 1791);
+
+    let data = std::fs::read("./data/test.heif").unwrap();
+    let ctx = HeifContext::read_from_io(Cursor::new(data))?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.width(), 1652);
+    Ok(())
+}
+
+#[test]
+fn primary_image_handle_of_empty_context() -> Result<()> {
+    let ctx = HeifContext::new()?;
+    assert!(ctx.primary_image_handle().is_err());
+    assert!(ctx.image_handle(1).is_err());
+    Ok(())
+}
+
 #[test]
 fn get_image_handler() -> Result<()> {
     let ctx = HeifContext::read_from_file("./data/test.heif")?;
@@ -78,6 +133,8 @@ fn get_image_handler() -> Result<()> {
     let mut image_ids: Vec<ItemId> = vec![0; 1];
     let count = handle.depth_image_ids(&mut image_ids);
     assert_eq!(count, 0);
+    assert!(handle.depth_images()?.is_empty());
+    assert!(handle.depth_representation_info().is_none());
     Ok(())
 }
 
@@ -104,6 +161,23 @@ fn get_thumbnail() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn decode_thumbnails() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+
+    let thumbnails = handle.thumbnails()?;
+    assert_eq!(thumbnails.len(), handle.number_of_thumbnails());
+    let thumb_handle = &thumbnails[0];
+    assert!(!thumb_handle.is_primary());
+
+    let image = lib_heif.decode(thumb_handle, ColorSpace::YCbCr(Chroma::C420), None)?;
+    assert_eq!(image.width(), 236);
+    assert_eq!(image.height(), 256);
+    Ok(())
+}
+
 #[test]
 fn get_exif() -> Result<()> {
     let ctx = HeifContext::read_from_file("./data/test.heif")?;
@@ -120,6 +194,13 @@ fn get_exif() -> Result<()> {
     assert_eq!(meta_content_type, Some(""));
     assert_eq!(handle.metadata_size(meta_ids[0]), 2330);
 
+    let blocks = handle.metadata_blocks()?;
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].id, meta_ids[0]);
+    assert_eq!(blocks[0].item_type, "Exif");
+    assert_eq!(blocks[0].content_type, "");
+    assert_eq!(blocks[0].data, handle.metadata(meta_ids[0])?);
+
     assert_eq!(handle.number_of_metadata_blocks(b"Unkn"), 0);
     let count = handle.metadata_block_ids(&mut meta_ids, b"Unkn");
     assert_eq!(count, 0);
@@ -136,6 +217,112 @@ fn get_exif() -> Result<()> {
     assert!(!is_le);
     assert_eq!(exif_fields.len(), 45);
 
+    let tiff_exif = handle.exif_metadata()?.unwrap();
+    assert_eq!(tiff_exif, exif[4..]);
+
+    Ok(())
+}
+
+#[test]
+fn get_exif_from_image_without_exif() -> Result<()> {
+    let ctx = HeifContext::read_from_file("./data/test_nclx.heif")?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.number_of_metadata_blocks(b"Exif"), 0);
+    assert!(handle.exif_metadata()?.is_none());
+    assert!(handle.metadata_blocks()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn image_handle_geometry_matches_decoded_image() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+
+    let image = lib_heif.decode(&handle, ColorSpace::YCbCr(Chroma::C420), None)?;
+    assert_eq!(image.width(), handle.width());
+    assert_eq!(image.height(), handle.height());
+    assert_eq!(
+        image.bits_per_pixel(Channel::Y),
+        Some(handle.luma_bits_per_pixel())
+    );
+    assert_eq!(
+        image.has_channel(Channel::Alpha),
+        handle.has_alpha_channel()
+    );
+    assert_eq!(image.channels(), vec![Channel::Y, Channel::Cb, Channel::Cr]);
+
+    // Image decoded without conversion has the preferred color space.
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+    assert_eq!(
+        image.color_space(),
+        Some(handle.preferred_decoding_colorspace()?)
+    );
+
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)?;
+    assert_eq!(image.channels(), vec![Channel::Interleaved]);
+
+    Ok(())
+}
+
+#[test]
+fn compare_pixels_of_images() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+    let color_space = ColorSpace::Rgb(RgbChroma::Rgb);
+
+    let image = lib_heif.decode(&handle, color_space, None)?;
+    let mut other_image = lib_heif.decode(&handle, color_space, None)?;
+    assert!(image.pixels_equal(&other_image));
+
+    let yuv_image = lib_heif.decode(&handle, ColorSpace::YCbCr(Chroma::C420), None)?;
+    assert!(!image.pixels_equal(&yuv_image));
+    let scaled_image = image.scale(800, 600, None)?;
+    assert!(!image.pixels_equal(&scaled_image));
+
+    let mut plane = other_image.plane_mut(Channel::Interleaved).unwrap();
+    let row = plane.row_mut(100).unwrap();
+    row[5] = row[5].wrapping_add(1);
+    assert!(!image.pixels_equal(&other_image));
+
+    Ok(())
+}
+
+#[test]
+fn interleaved_rgba_rows() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+
+    let image = lib_heif.decode(&handle, ColorSpace::YCbCr(Chroma::C420), None)?;
+    assert!(image.interleaved_rgba().is_err());
+
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)?;
+    let plane = image.interleaved_rgba()?;
+    assert_eq!(plane.width, 1652);
+    assert_eq!(plane.height, 1791);
+    assert_eq!(plane.data.len(), plane.stride * 1791);
+    assert_eq!(plane.row_size(), 1652 * 4);
+    for y in [0, 1000, 1790] {
+        let row = plane.row(y).unwrap();
+        assert_eq!(row.len(), 1652 * 4);
+        let start = y as usize * plane.stride;
+        assert_eq!(row, &plane.data[start..start + 1652 * 4]);
+    }
+    assert!(plane.row(1791).is_none());
+
+    let mut image = image;
+    let mut plane = image.plane_mut(Channel::Interleaved).unwrap();
+    plane.row_mut(1790).unwrap().fill(0);
+    assert!(plane.row_mut(1791).is_none());
+    assert!(image
+        .interleaved_rgba()?
+        .row(1790)
+        .unwrap()
+        .iter()
+        .all(|&v| v == 0));
+
     Ok(())
 }
 
@@ -161,8 +348,17 @@ fn decode_and_scale_image() -> Result<()> {
     assert_eq!(cr_plane.width, 1652 / 2);
     assert_eq!(cr_plane.height, 1791 / 2 + 1);
 
+    let y_plane = src_img.plane(Channel::Y).unwrap();
+    assert_eq!(y_plane.width, 1652);
+    assert!(y_plane.stride >= 1652);
+    assert_eq!(y_plane.data.len(), y_plane.stride * 1791);
+    assert!(src_img.plane(Channel::R).is_none());
+
     // Scale the image
     let img = src_img.scale(1024, 800, None)?;
+    assert_eq!(img.color_space(), src_img.color_space());
+    assert_eq!(img.width(), 1024);
+    assert_eq!(img.height(), 800);
     let planes = img.planes();
     let y_plane = planes.y.unwrap();
     assert_eq!(y_plane.width, 1024);
@@ -181,6 +377,40 @@ fn decode_and_scale_image() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn decode_into_buffer() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+    let color_space = ColorSpace::Rgb(RgbChroma::Rgb);
+
+    let stride = 1652 * 3 + 4;
+    let mut buf = vec![0; stride * 1791];
+    lib_heif.decode_into(&handle, color_space, None, &mut buf, stride)?;
+
+    let image = lib_heif.decode(&handle, color_space, None)?;
+    let plane = image.plane(Channel::Interleaved).unwrap();
+    for y in [0, 1000, 1790] {
+        let expected = &plane.data[y * plane.stride..y * plane.stride + 1652 * 3];
+        assert_eq!(&buf[y * stride..y * stride + 1652 * 3], expected);
+        assert_eq!(&buf[y * stride + 1652 * 3..(y + 1) * stride], &[0; 4]);
+    }
+
+    // Buffer is too small
+    let res = lib_heif.decode_into(&handle, color_space, None, &mut buf[1..], stride);
+    assert!(res.is_err());
+    // Color space with several planes
+    let res = lib_heif.decode_into(
+        &handle,
+        ColorSpace::YCbCr(Chroma::C420),
+        None,
+        &mut buf,
+        stride,
+    );
+    assert!(res.is_err());
+    Ok(())
+}
+
 #[test]
 fn top_decode_heic() -> Result<()> {
     let lib_heif = LibHeif::new();
@@ -225,9 +455,50 @@ fn sequence_of_images() -> Result<()> {
         assert_eq!(handle.height(), 360);
     }
 
+    let handles = ctx.top_level_image_handles();
+    assert_eq!(handles.len(), 4);
+    for (handle, &image_id) in handles.iter().zip(image_ids[0..4].iter()) {
+        assert_eq!(handle.item_id(), image_id);
+    }
+
+    // Handles are compared by item ID
+    let primary_handle = ctx.primary_image_handle()?;
+    assert!(handles.contains(&primary_handle));
+    assert!(primary_handle == ctx.image_handle(primary_handle.item_id())?);
+    assert!(handles[0] != handles[1]);
+    let unique_handles: std::collections::HashSet<ImageHandle> = ctx
+        .top_level_image_handles()
+        .into_iter()
+        .chain(ctx.top_level_image_handles())
+        .collect();
+    assert_eq!(unique_handles.len(), 4);
+
     Ok(())
 }
 
+#[test]
+fn read_same_data_in_parallel_threads() -> Result<()> {
+    let data = std::fs::read("./data/sequence.heif").unwrap();
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let data = &data;
+                scope.spawn(move || -> Result<u32> {
+                    let lib_heif = LibHeif::new();
+                    let ctx = HeifContext::read_from_bytes(data)?;
+                    let handle = ctx.top_level_image_handles().swap_remove(i);
+                    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+                    Ok(image.width())
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap()?, 480);
+        }
+        Ok(())
+    })
+}
+
 #[test]
 fn test_check_file_type() {
     let mut data = vec![0u8; 16];
@@ -242,6 +513,29 @@ fn test_check_file_type() {
     assert_eq!(check_file_type(&data[..12]), FileTypeResult::Supported);
 }
 
+#[test]
+fn test_file_mime_type() {
+    assert_eq!(file_mime_type(&[0u8; 16]), None);
+    assert_eq!(file_mime_type(&[]), None);
+
+    let data = std::fs::read("./data/test.heic").unwrap();
+    assert_eq!(file_mime_type(&data[..12]), Some("image/heic"));
+    let data = std::fs::read("./data/test_nclx.avif").unwrap();
+    assert_eq!(file_mime_type(&data), Some("image/avif"));
+}
+
+#[test]
+fn test_file_compression_format() {
+    let data = std::fs::read("./data/test.heic").unwrap();
+    assert_eq!(file_compression_format(&data), CompressionFormat::Hevc);
+    let data = std::fs::read("./data/test.heif").unwrap();
+    assert_eq!(file_compression_format(&data), CompressionFormat::Av1);
+    assert_eq!(
+        file_compression_format(b"invalid data"),
+        CompressionFormat::Undefined
+    );
+}
+
 #[test]
 fn test_raw_color_profile_of_image_handle() -> Result<()> {
     let ctx = HeifContext::read_from_file("./data/test.heif")?;
@@ -251,6 +545,10 @@ fn test_raw_color_profile_of_image_handle() -> Result<()> {
     assert_eq!(raw_profile.profile_type(), color_profile_types::PROF);
     assert_eq!(raw_profile.data.len(), 536);
 
+    assert_eq!(handle.color_profile_type(), color_profile_types::PROF);
+    let icc_profile = handle.icc_profile()?.unwrap();
+    assert_eq!(icc_profile, raw_profile.data);
+
     let nclx_profile = handle.color_profile_nclx();
     assert!(nclx_profile.is_none());
     Ok(())
@@ -263,6 +561,8 @@ fn test_nclx_color_profile_of_image_handle() -> Result<()> {
 
     let raw_profile = handle.color_profile_raw();
     assert!(raw_profile.is_none());
+    assert_eq!(handle.color_profile_type(), color_profile_types::NCLX);
+    assert!(handle.icc_profile()?.is_none());
 
     let nclx_profile = handle.color_profile_nclx().unwrap();
     assert_eq!(nclx_profile.profile_type(), color_profile_types::NCLX);
@@ -297,6 +597,7 @@ fn test_raw_color_profile_of_image() -> Result<()> {
     let handle = ctx.primary_image_handle()?;
     let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
     assert!(image.color_profile_raw().is_some());
+    assert_eq!(image.color_profile_type(), color_profile_types::PROF);
     assert!(image.color_profile_nclx().is_none());
     Ok(())
 }
@@ -317,10 +618,15 @@ fn test_read_avif_image() -> Result<()> {
 
     let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
     assert_eq!(image.color_space(), Some(ColorSpace::Rgb(RgbChroma::C444)));
+    assert_eq!(image.bits_per_pixel(Channel::R), Some(12));
+    assert_eq!(image.storage_bits_per_pixel(Channel::R), Some(16));
+    assert_eq!(image.bits_per_pixel(Channel::Y), None);
     let planes = image.planes();
     let r_plane = planes.r.unwrap();
     assert_eq!(r_plane.width, 2048);
     assert_eq!(r_plane.height, 1440);
+    assert_eq!(r_plane.bits_per_pixel, 12);
+    assert!(r_plane.stride >= 2048 * 2);
 
     Ok(())
 }
@@ -350,3 +656,94 @@ fn test_decoding_options() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn decode_hdr_image_into_8bit() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test_nclx.avif")?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.luma_bits_per_pixel(), 12);
+
+    let mut dec_options = DecodingOptions::new().unwrap();
+    dec_options.set_convert_hdr_to_8bit(true);
+    assert!(dec_options.convert_hdr_to_8bit());
+    let color_space = ColorSpace::Rgb(RgbChroma::C444);
+    let image = lib_heif.decode(&handle, color_space, Some(dec_options))?;
+    assert_eq!(image.bits_per_pixel(Channel::R), Some(8));
+    assert_eq!(image.storage_bits_per_pixel(Channel::R), Some(8));
+
+    Ok(())
+}
+
+#[test]
+fn decode_with_progress_callback() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let handle = ctx.primary_image_handle()?;
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let events_clone = events.clone();
+    let mut dec_options = DecodingOptions::new().unwrap();
+    dec_options.set_progress_callback(Some(Box::new(move |event| {
+        events_clone.borrow_mut().push(event)
+    })));
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, Some(dec_options))?;
+    assert_eq!(image.width(), 1652);
+
+    // The image is not a grid image, so libheif may report nothing.
+    let events = events.borrow();
+    if let Some(event) = events.first() {
+        assert!(matches!(event, ProgressEvent::Start { .. }));
+        assert!(matches!(events.last(), Some(ProgressEvent::End { .. })));
+    }
+
+    let mut dec_options = DecodingOptions::new().unwrap();
+    dec_options.set_progress_callback(Some(Box::new(|_| {})));
+    dec_options.set_progress_callback(None);
+    lib_heif.decode(&handle, ColorSpace::Undefined, Some(dec_options))?;
+
+    Ok(())
+}
+
+#[test]
+fn raw_pointers() -> Result<()> {
+    let mut ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let count =
+        unsafe { libheif_sys::heif_context_get_number_of_top_level_images(ctx.as_raw_mut()) };
+    assert_eq!(count as usize, ctx.number_of_top_level_images());
+
+    let handle = ctx.primary_image_handle()?;
+    let width = unsafe { libheif_sys::heif_image_handle_get_width(handle.as_raw()) };
+    assert_eq!(width as u32, handle.width());
+    Ok(())
+}
+
+#[test]
+fn create_and_drop_many_handles() -> Result<()> {
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let primary_id = ctx.primary_image_handle()?.item_id();
+    for _ in 0..10_000 {
+        let handle = ctx.image_handle(primary_id)?;
+        let thumbnails = handle.thumbnails()?;
+        assert_eq!(thumbnails.len(), 1);
+        drop(handle);
+        // Derived handles are valid after dropping of the master handle.
+        assert_eq!(thumbnails[0].width(), 236);
+    }
+    Ok(())
+}
+
+#[test]
+fn context_stats() -> Result<()> {
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let stats = ctx.stats();
+    assert_eq!(stats.top_level_images, 1);
+    assert_eq!(stats.thumbnails, 1);
+    assert_eq!(stats.auxiliary_images, 0);
+    assert_eq!(stats.metadata_blocks, 1);
+    assert_eq!(stats.metadata_size, 2330);
+
+    let ctx = HeifContext::read_from_file("./data/sequence.heif")?;
+    assert_eq!(ctx.stats().top_level_images, 4);
+    Ok(())
+}
@@ -0,0 +1,19 @@
+#![cfg(feature = "tokio")]
+use libheif_rs::{HeifContext, HeifErrorCode, Result};
+
+#[tokio::test]
+async fn read_from_async() -> Result<()> {
+    let file = tokio::fs::File::open("./data/test.heif").await.unwrap();
+    let ctx = HeifContext::read_from_async(file).await?;
+    let handle = ctx.primary_image_handle()?;
+    assert_eq!(handle.width(), 1652);
+    assert_eq!(handle.height(), 1791);
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_invalid_data_from_async() {
+    let data: &[u8] = b"invalid data";
+    let err = HeifContext::read_from_async(data).await.err().unwrap();
+    assert!(matches!(err.code, HeifErrorCode::InvalidInput));
+}
@@ -1,7 +1,11 @@
 use libheif_rs::{
-    Channel, ChromaDownsamplingAlgorithm, ChromaUpsamplingAlgorithm, ColorSpace, CompressionFormat,
-    EncoderParameterValue, EncoderQuality, EncodingOptions, HeifContext, Image, ImageOrientation,
-    LibHeif, Result, RgbChroma,
+    color_profile_types, AuxiliaryImagesFilter, Channel, Chroma, ChromaDownsamplingAlgorithm,
+    ChromaUpsamplingAlgorithm, ColorPrimaries, ColorProfileNCLX, ColorProfileRaw, ColorSpace,
+    CompressionFormat, ContentLightLevel, DecodingOptions, EncoderParameterType,
+    EncoderParameterValidValues, EncoderParameterValue, EncoderQuality, EncoderSpeed,
+    EncodingOptions, HeifContext, HeifError, HeifErrorCode, HeifErrorSubCode, Image,
+    ImageOrientation, LibHeif, MasteringDisplayColourVolume, MatrixCoefficients, Result, RgbChroma,
+    TransferCharacteristics, Writer,
 };
 
 fn create_image(width: u32, height: u32) -> Result<Image> {
@@ -26,6 +30,37 @@ fn create_image(width: u32, height: u32) -> Result<Image> {
     Ok(image)
 }
 
+#[test]
+fn decode_with_ignored_transformations() -> Result<()> {
+    let image = create_image(64, 32)?;
+    let lib_heif = LibHeif::new();
+    let mut context = HeifContext::new()?;
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut encoding_options = EncodingOptions::new()?;
+    encoding_options.set_image_orientation(ImageOrientation::Rotate90Cw);
+    context.encode_image(&image, &mut encoder, Some(encoding_options))?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+
+    // Rotation is applied by default.
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+    assert_eq!(image.width(), 32);
+    assert_eq!(image.height(), 64);
+
+    let mut dec_options = DecodingOptions::new().unwrap();
+    dec_options.set_ignore_transformations(true);
+    assert!(dec_options.ignore_transformations());
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, Some(dec_options))?;
+    assert_eq!(image.width(), handle.ispe_width() as u32);
+    assert_eq!(image.height(), handle.ispe_height() as u32);
+    assert_eq!(image.width(), 64);
+    assert_eq!(image.height(), 32);
+
+    Ok(())
+}
+
 #[test]
 fn create_and_encode_image() -> Result<()> {
     let width = 640;
@@ -57,6 +92,124 @@ fn create_and_encode_image() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn write_to_file_with_nul_in_name() -> Result<()> {
+    let context = HeifContext::new()?;
+    assert!(context.write_to_file("a\0b.heic").is_err());
+    Ok(())
+}
+
+#[test]
+fn crop_image() -> Result<()> {
+    let width = 64;
+    let height = 48;
+    let mut image = Image::new(width, height, ColorSpace::Monochrome)?;
+    image.create_plane(Channel::Y, width, height, 8)?;
+    let plane = image.plane_mut(Channel::Y).unwrap();
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            plane.data[y * plane.stride + x] = (x + y) as u8;
+        }
+    }
+
+    assert!(image.crop(32, 0, 33, 10).is_err());
+    assert!(image.crop(0, 48, 10, 1).is_err());
+    assert!(image.crop(0, 0, 0, 10).is_err());
+    assert!(image.crop(u32::MAX, 0, 2, 10).is_err());
+
+    let cropped = image.crop(10, 6, 50, 40)?;
+    assert_eq!(cropped.width(), 50);
+    assert_eq!(cropped.height(), 40);
+    let plane = cropped.plane(Channel::Y).unwrap();
+    assert_eq!(plane.width, 50);
+    assert_eq!(plane.height, 40);
+    for y in 0..40 {
+        for x in 0..50 {
+            assert_eq!(plane.data[y * plane.stride + x], (x + 10 + y + 6) as u8);
+        }
+    }
+    // Source image is not changed
+    assert_eq!(image.width(), 64);
+    assert_eq!(image.height(), 48);
+    Ok(())
+}
+
+#[test]
+fn crop_image_with_subsampled_chroma() -> Result<()> {
+    let width = 16;
+    let height = 12;
+    let mut image = Image::new(width, height, ColorSpace::YCbCr(Chroma::C420))?;
+    image.create_plane(Channel::Y, width, height, 8)?;
+    image.create_plane(Channel::Cb, width / 2, height / 2, 8)?;
+    image.create_plane(Channel::Cr, width / 2, height / 2, 8)?;
+    for (channel, base) in [(Channel::Y, 0), (Channel::Cb, 100), (Channel::Cr, 200)] {
+        let plane = image.plane_mut(channel).unwrap();
+        for y in 0..plane.height as usize {
+            for x in 0..plane.width as usize {
+                plane.data[y * plane.stride + x] = base + (x + y * 8) as u8;
+            }
+        }
+    }
+
+    // Odd offsets split chroma samples
+    assert!(image.crop(1, 0, 8, 8).is_err());
+    assert!(image.crop(0, 3, 8, 8).is_err());
+
+    let cropped = image.crop(4, 2, 7, 5)?;
+    assert_eq!(cropped.color_space(), Some(ColorSpace::YCbCr(Chroma::C420)));
+    let y_plane = cropped.plane(Channel::Y).unwrap();
+    assert_eq!((y_plane.width, y_plane.height), (7, 5));
+    assert_eq!(y_plane.data[0], 4 + 2 * 8);
+    for (channel, base) in [(Channel::Cb, 100), (Channel::Cr, 200)] {
+        let plane = cropped.plane(channel).unwrap();
+        // Size of chroma planes is rounded up
+        assert_eq!((plane.width, plane.height), (4, 3));
+        for y in 0..3 {
+            let row = &plane.data[y * plane.stride..][..4];
+            let expected: Vec<u8> = (0..4).map(|x| base + (x + 2 + (y + 1) * 8) as u8).collect();
+            assert_eq!(row, expected.as_slice());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn create_encode_and_decode_red_image() -> Result<()> {
+    let width = 16;
+    let height = 16;
+    let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgb))?;
+    image.create_plane(Channel::Interleaved, width, height, 8)?;
+    let plane = image.plane_mut(Channel::Interleaved).unwrap();
+    for y in 0..height as usize {
+        let row = &mut plane.data[y * plane.stride..];
+        for pixel in row[..width as usize * 3].chunks_exact_mut(3) {
+            pixel.copy_from_slice(&[255, 0, 0]);
+        }
+    }
+
+    let lib_heif = LibHeif::new();
+    let mut context = HeifContext::new()?;
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    encoder.set_quality(EncoderQuality::LossLess)?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)?;
+    assert_eq!(image.width(), width);
+    assert_eq!(image.height(), height);
+    let plane = image.plane(Channel::Interleaved).unwrap();
+    let pixel_pos = 8 * plane.stride + 8 * 3;
+    let pixel = &plane.data[pixel_pos..pixel_pos + 3];
+    // Color conversion RGB -> YCbCr -> RGB may change values a little.
+    assert!(pixel[0] >= 250, "{:?}", pixel);
+    assert!(pixel[1] <= 5, "{:?}", pixel);
+    assert!(pixel[2] <= 5, "{:?}", pixel);
+
+    Ok(())
+}
+
 #[test]
 fn create_and_encode_monochrome_image() -> Result<()> {
     let width = 640;
@@ -88,7 +241,149 @@ fn create_and_encode_monochrome_image() -> Result<()> {
     let encoding_options = EncodingOptions::new()?;
 
     context.encode_image(&image, &mut encoder, Some(encoding_options))?;
-    let _buf = context.write_to_bytes()?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(
+        handle.preferred_decoding_colorspace()?,
+        ColorSpace::Monochrome
+    );
+    let decoded_image = lib_heif.decode(&handle, ColorSpace::Monochrome, None)?;
+    assert_eq!(decoded_image.color_space(), Some(ColorSpace::Monochrome));
+    assert_eq!(decoded_image.channels(), vec![Channel::Y]);
+    let src_plane = image.plane(Channel::Y).unwrap();
+    let plane = decoded_image.plane(Channel::Y).unwrap();
+    assert_eq!(plane.bits_per_pixel, 8);
+    for y in 0..height as usize {
+        let src_row = &src_plane.data[y * src_plane.stride..][..width as usize];
+        let row = &plane.data[y * plane.stride..][..width as usize];
+        assert_eq!(row, src_row);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn set_primary_image() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    let first_handle = context.encode_image(&create_image(64, 64)?, &mut encoder, None)?;
+    let second_handle = context.encode_image(&create_image(32, 48)?, &mut encoder, None)?;
+    assert!(!second_handle.is_primary());
+
+    context.set_primary_image(&second_handle)?;
+    assert!(second_handle.is_primary());
+    assert!(!first_handle.is_primary());
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    assert_eq!(context.number_of_top_level_images(), 2);
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.item_id(), second_handle.item_id());
+    assert_eq!(handle.width(), 32);
+    assert_eq!(handle.height(), 48);
+
+    Ok(())
+}
+
+#[test]
+fn encode_thumbnail() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let image = create_image(128, 64)?;
+    let mut context = HeifContext::new()?;
+    let handle = context.encode_image(&image, &mut encoder, None)?;
+
+    // Image fits into the bounding box
+    let thumb_handle = context.encode_thumbnail(&image, &handle, 128, &mut encoder, None)?;
+    assert!(thumb_handle.is_none());
+
+    let thumb_handle = context
+        .encode_thumbnail(&image, &handle, 32, &mut encoder, None)?
+        .unwrap();
+    assert_eq!(thumb_handle.width(), 32);
+    assert_eq!(thumb_handle.height(), 16);
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.number_of_thumbnails(), 1);
+    let thumbnails = handle.thumbnails()?;
+    assert_eq!(thumbnails[0].width(), 32);
+    assert_eq!(thumbnails[0].height(), 16);
+
+    Ok(())
+}
+
+#[test]
+fn alpha_channel_as_auxiliary_image() -> Result<()> {
+    let width = 64;
+    let height = 64;
+    let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgba))?;
+    image.create_plane(Channel::Interleaved, width, height, 8)?;
+    let plane = image.plane_mut(Channel::Interleaved).unwrap();
+    for pixel in plane.data.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&[255, 0, 0, 128]);
+    }
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert!(handle.has_alpha_channel());
+    assert!(handle.auxiliary_type().is_none());
+
+    let filter = AuxiliaryImagesFilter::default();
+    assert_eq!(handle.number_of_auxiliary_images(filter), 1);
+    let aux_handles = handle.auxiliary_images(filter)?;
+    assert_eq!(aux_handles.len(), 1);
+    let aux_type = aux_handles[0].auxiliary_type().unwrap();
+    assert!(aux_type.ends_with("alpha"), "{}", aux_type);
+
+    let filter = AuxiliaryImagesFilter {
+        omit_alpha: true,
+        ..Default::default()
+    };
+    assert_eq!(handle.number_of_auxiliary_images(filter), 0);
+    assert!(handle.auxiliary_images(filter)?.is_empty());
+    // Alpha channel is not a gain map
+    assert!(handle.gain_map()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn premultiplied_alpha_round_trip() -> Result<()> {
+    let width = 32;
+    let height = 32;
+    let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgba))?;
+    image.create_plane(Channel::Interleaved, width, height, 8)?;
+    let plane = image.plane_mut(Channel::Interleaved).unwrap();
+    for pixel in plane.data.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&[64, 0, 0, 128]);
+    }
+    assert!(!image.is_premultiplied_alpha());
+    image.set_premultiplied_alpha(true);
+    assert!(image.is_premultiplied_alpha());
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert!(handle.has_alpha_channel());
+    assert!(handle.is_premultiplied_alpha());
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)?;
+    assert!(image.is_premultiplied_alpha());
 
     Ok(())
 }
@@ -103,6 +398,17 @@ fn set_encoder_param() -> Result<()> {
     let mut context = HeifContext::new()?;
     let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
     encoder.set_parameter_value("speed", EncoderParameterValue::Int(5))?;
+    assert_eq!(
+        encoder.parameter("speed")?,
+        Some(EncoderParameterValue::Int(5))
+    );
+    encoder.set_parameter("speed", "6")?;
+    assert_eq!(
+        encoder.parameter("speed")?,
+        Some(EncoderParameterValue::Int(6))
+    );
+    assert!(encoder.set_parameter("unknown-parameter", "1").is_err());
+    assert!(encoder.set_parameter("speed\0", "1").is_err());
     let encoding_options = EncodingOptions::new()?;
     context.encode_image(&image, &mut encoder, Some(encoding_options))?;
 
@@ -178,6 +484,148 @@ fn add_metadata() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn exif_metadata_round_trip() -> Result<()> {
+    let src_context = HeifContext::read_from_file("./data/test.heif")?;
+    let src_handle = src_context.primary_image_handle()?;
+    let exif = src_handle.exif_metadata()?.unwrap();
+
+    let image = create_image(64, 64)?;
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    let handle = context.encode_image(&image, &mut encoder, None)?;
+    context.add_exif_metadata(&handle, &exif)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.exif_metadata()?, Some(exif));
+
+    Ok(())
+}
+
+#[test]
+fn xmp_metadata_round_trip() -> Result<()> {
+    let image = create_image(64, 64)?;
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let xmp_data = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"></x:xmpmeta>"#;
+
+    let mut context = HeifContext::new()?;
+    let handle = context.encode_image(&image, &mut encoder, None)?;
+    assert!(handle.xmp_metadata()?.is_none());
+    context.add_xmp_metadata(&handle, xmp_data)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let xmp = handle.xmp_metadata()?.unwrap();
+    assert_eq!(&xmp, xmp_data);
+
+    // Write read XMP into a new file
+    let mut context = HeifContext::new()?;
+    let handle = context.encode_image(&image, &mut encoder, None)?;
+    context.add_xmp_metadata(&handle, &xmp)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.xmp_metadata()?, Some(xmp));
+
+    Ok(())
+}
+
+#[test]
+fn icc_profile_round_trip() -> Result<()> {
+    let ctx = HeifContext::read_from_file("./data/test.heif")?;
+    let src_handle = ctx.primary_image_handle()?;
+    let icc_data = src_handle.icc_profile()?.unwrap();
+
+    let mut image = create_image(64, 64)?;
+    let profile = ColorProfileRaw::new(color_profile_types::PROF, icc_data.clone());
+    image.set_color_profile_raw(&profile)?;
+    assert_eq!(image.color_profile_type(), color_profile_types::PROF);
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.color_profile_type(), color_profile_types::PROF);
+    assert_eq!(handle.icc_profile()?, Some(icc_data));
+
+    Ok(())
+}
+
+#[test]
+fn nclx_profile_round_trip() -> Result<()> {
+    let mut image = create_image(64, 64)?;
+    let mut profile = ColorProfileNCLX::new().unwrap();
+    profile.set_color_primaries(ColorPrimaries::ITU_R_BT_2020_2_and_2100_0);
+    profile.set_transfer_characteristics(TransferCharacteristics::ITU_R_BT_2100_0_PQ);
+    profile.set_matrix_coefficients(MatrixCoefficients::ITU_R_BT_2020_2_NonConstantLuminance);
+    profile.set_full_range_flag(1);
+    image.set_color_profile_nclx(&profile)?;
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let profile = handle.color_profile_nclx().unwrap();
+    assert_eq!(
+        profile.color_primaries(),
+        ColorPrimaries::ITU_R_BT_2020_2_and_2100_0
+    );
+    assert_eq!(
+        profile.transfer_characteristics(),
+        TransferCharacteristics::ITU_R_BT_2100_0_PQ
+    );
+    assert_eq!(
+        profile.matrix_coefficients(),
+        MatrixCoefficients::ITU_R_BT_2020_2_NonConstantLuminance
+    );
+    assert_eq!(profile.full_range_flag(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn output_nclx_profile() -> Result<()> {
+    let image = create_image(64, 64)?;
+    let mut profile = ColorProfileNCLX::new().unwrap();
+    profile.set_color_primaries(ColorPrimaries::ITU_R_BT_2020_2_and_2100_0);
+    profile.set_full_range_flag(1);
+    let mut encoding_options = EncodingOptions::new()?;
+    assert!(encoding_options.output_nclx_profile().is_none());
+    encoding_options.set_output_nclx_profile(Some(profile));
+    assert!(encoding_options.output_nclx_profile().is_some());
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, Some(encoding_options))?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let profile = handle.color_profile_nclx().unwrap();
+    assert_eq!(
+        profile.color_primaries(),
+        ColorPrimaries::ITU_R_BT_2020_2_and_2100_0
+    );
+    assert_eq!(profile.full_range_flag(), 1);
+
+    Ok(())
+}
+
 #[test]
 fn test_encoder_hevc() -> Result<()> {
     let lib_heif = LibHeif::new();
@@ -206,6 +654,12 @@ fn test_encoder_hevc() -> Result<()> {
     ];
     assert_eq!(params, expect);
 
+    encoder.set_speed(EncoderSpeed::Fastest)?;
+    assert_eq!(
+        encoder.parameter("preset")?,
+        Some(EncoderParameterValue::String("ultrafast".to_string()))
+    );
+
     assert_eq!(
         encoder.parameter("lossless")?,
         Some(EncoderParameterValue::Bool(false))
@@ -228,6 +682,45 @@ fn test_encoder_av1() -> Result<()> {
     let params = encoder.parameters_names();
     assert!(params.len() >= 13);
 
+    let params = encoder.list_parameters();
+    assert_eq!(params.len(), encoder.parameters_names().len());
+    let speed = params.iter().find(|p| p.name == "speed").unwrap();
+    assert_eq!(speed.parameter_type, EncoderParameterType::Int);
+    assert!(matches!(
+        speed.valid_values,
+        EncoderParameterValidValues::IntRange {
+            minimum: Some(0),
+            maximum: Some(_),
+        }
+    ));
+    let max_speed = match speed.valid_values {
+        EncoderParameterValidValues::IntRange {
+            maximum: Some(v), ..
+        } => v,
+        _ => unreachable!(),
+    };
+    encoder.set_speed(EncoderSpeed::Slowest)?;
+    assert_eq!(
+        encoder.parameter("speed")?,
+        Some(EncoderParameterValue::Int(0))
+    );
+    encoder.set_speed(EncoderSpeed::Fastest)?;
+    assert_eq!(
+        encoder.parameter("speed")?,
+        Some(EncoderParameterValue::Int(max_speed))
+    );
+
+    let chroma = params.iter().find(|p| p.name == "chroma").unwrap();
+    assert_eq!(chroma.parameter_type, EncoderParameterType::String);
+    match &chroma.valid_values {
+        EncoderParameterValidValues::Strings(values) => {
+            assert!(values.iter().any(|v| v == "420"));
+        }
+        v => panic!("Unexpected valid values of 'chroma' parameter: {:?}", v),
+    }
+    let lossless = params.iter().find(|p| p.name == "lossless").unwrap();
+    assert_eq!(lossless.parameter_type, EncoderParameterType::Bool);
+
     assert_eq!(
         encoder.parameter("lossless")?,
         Some(EncoderParameterValue::Bool(false))
@@ -238,6 +731,45 @@ fn test_encoder_av1() -> Result<()> {
         encoder.parameter("lossless")?,
         Some(EncoderParameterValue::Bool(true))
     );
+
+    encoder.set_quality(EncoderQuality::Lossy(80))?;
+    assert_eq!(
+        encoder.parameter("lossless")?,
+        Some(EncoderParameterValue::Bool(false))
+    );
+    assert_eq!(
+        encoder.parameter("quality")?,
+        Some(EncoderParameterValue::Int(80))
+    );
+    assert!(encoder.set_quality(EncoderQuality::Lossy(101)).is_err());
+
+    encoder.set_logging_level(0)?;
+    assert!(encoder.set_logging_level(5).is_err());
+    Ok(())
+}
+
+#[test]
+fn encode_in_other_thread() -> Result<()> {
+    let lib_heif = LibHeif::new();
+    let encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let context = HeifContext::new()?;
+    let (mut encoder, mut context) = std::thread::scope(|scope| {
+        scope
+            .spawn(move || -> Result<_> {
+                let mut encoder = encoder;
+                let mut context = context;
+                let image = create_image(64, 64)?;
+                context.encode_image(&image, &mut encoder, None)?;
+                Ok((encoder, context))
+            })
+            .join()
+            .unwrap()
+    })?;
+
+    context.encode_image(&create_image(32, 32)?, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+    let context = HeifContext::read_from_bytes(&buf)?;
+    assert_eq!(context.number_of_top_level_images(), 2);
     Ok(())
 }
 
@@ -264,3 +796,244 @@ fn test_encoding_options() -> Result<()> {
 
     Ok(())
 }
+
+#[derive(Default)]
+struct ChunksWriter {
+    chunks: Vec<Vec<u8>>,
+}
+
+impl Writer for ChunksWriter {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.chunks.push(data.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn write_to_writer() -> Result<()> {
+    let image = create_image(64, 64)?;
+    let lib_heif = LibHeif::new();
+    let mut context = HeifContext::new()?;
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    context.encode_image(&image, &mut encoder, None)?;
+
+    let mut writer = ChunksWriter::default();
+    context.write_to_writer(&mut writer)?;
+    let buf = writer.chunks.concat();
+    assert_eq!(buf, context.write_to_bytes()?);
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.width(), 64);
+    assert_eq!(handle.height(), 64);
+
+    Ok(())
+}
+
+/// Writer that fails after receiving the given number of chunks.
+struct FailingWriter {
+    chunks_before_fail: usize,
+}
+
+impl Writer for FailingWriter {
+    fn write(&mut self, _data: &[u8]) -> Result<()> {
+        if self.chunks_before_fail == 0 {
+            return Err(HeifError {
+                code: HeifErrorCode::EncodingError,
+                sub_code: HeifErrorSubCode::CannotWriteOutputData,
+                message: "Disk is full".to_string(),
+            });
+        }
+        self.chunks_before_fail -= 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn write_to_failing_writer() -> Result<()> {
+    let image = create_image(64, 64)?;
+    let lib_heif = LibHeif::new();
+    let mut context = HeifContext::new()?;
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    context.encode_image(&image, &mut encoder, None)?;
+
+    // libheif passes the whole file into the writer at once.
+    let mut writer = FailingWriter {
+        chunks_before_fail: 0,
+    };
+    let err = context.write_to_writer(&mut writer).unwrap_err();
+    assert!(matches!(err.code, HeifErrorCode::EncodingError));
+    assert!(matches!(
+        err.sub_code,
+        HeifErrorSubCode::CannotWriteOutputData
+    ));
+    assert_eq!(err.message, "Disk is full");
+
+    Ok(())
+}
+
+#[test]
+fn write_to_io() -> Result<()> {
+    let image = create_image(64, 64)?;
+    let lib_heif = LibHeif::new();
+    let mut context = HeifContext::new()?;
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    context.encode_image(&image, &mut encoder, None)?;
+
+    let mut buf = Vec::new();
+    context.write_to_io(&mut buf)?;
+    assert_eq!(buf, context.write_to_bytes()?);
+
+    let mut small_buf = [0u8; 16];
+    let err = context
+        .write_to_io(&mut small_buf.as_mut_slice())
+        .unwrap_err();
+    assert!(matches!(
+        err.sub_code,
+        HeifErrorSubCode::CannotWriteOutputData
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn ispe_size_of_rotated_image() -> Result<()> {
+    let image = create_image(64, 32)?;
+    let lib_heif = LibHeif::new();
+    let mut context = HeifContext::new()?;
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut encoding_options = EncodingOptions::new()?;
+    encoding_options.set_image_orientation(ImageOrientation::Rotate90Cw);
+    context.encode_image(&image, &mut encoder, Some(encoding_options))?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    assert_eq!(handle.width(), 32);
+    assert_eq!(handle.height(), 64);
+    assert_eq!(handle.ispe_width(), 64);
+    assert_eq!(handle.ispe_height(), 32);
+
+    Ok(())
+}
+
+#[test]
+fn write_plane() -> Result<()> {
+    let (width, height) = (5u32, 3u32);
+    let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgb))?;
+    image.create_plane(Channel::Interleaved, width, height, 8)?;
+
+    let src_stride = 16;
+    let src: Vec<u8> = (0..src_stride * height as usize).map(|i| i as u8).collect();
+    image.write_plane(Channel::Interleaved, &src, src_stride)?;
+
+    let plane = image.plane(Channel::Interleaved).unwrap();
+    let row_size = width as usize * 3;
+    for y in 0..height as usize {
+        let dst_row = &plane.data[y * plane.stride..][..row_size];
+        assert_eq!(dst_row, &src[y * src_stride..][..row_size]);
+    }
+
+    // Stride is smaller than size of row.
+    assert!(image
+        .write_plane(Channel::Interleaved, &src, row_size - 1)
+        .is_err());
+    // Buffer doesn't contain the last row.
+    assert!(image
+        .write_plane(Channel::Interleaved, &src[..src_stride * 2], src_stride)
+        .is_err());
+    // Image has no such plane.
+    assert!(image.write_plane(Channel::Y, &src, src_stride).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn encode_images() -> Result<()> {
+    let sizes = [(64, 64), (32, 48), (48, 32)];
+    let images = sizes
+        .iter()
+        .map(|&(width, height)| create_image(width, height))
+        .collect::<Result<Vec<_>>>()?;
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::default();
+    let handles = context.encode_images(&images, &mut encoder, None)?;
+    assert_eq!(handles.len(), 3);
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    assert_eq!(context.number_of_top_level_images(), 3);
+    let handle = context.primary_image_handle()?;
+    assert_eq!((handle.width(), handle.height()), sizes[0]);
+    let read_sizes: Vec<_> = context
+        .top_level_image_handles()
+        .iter()
+        .map(|handle| (handle.width(), handle.height()))
+        .collect();
+    assert_eq!(read_sizes, sizes);
+
+    Ok(())
+}
+
+#[test]
+fn pixel_aspect_ratio_round_trip() -> Result<()> {
+    let mut image = create_image(64, 64)?;
+    assert_eq!(image.pixel_aspect_ratio(), (1, 1));
+    image.set_pixel_aspect_ratio(4, 3);
+    assert_eq!(image.pixel_aspect_ratio(), (4, 3));
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+    assert_eq!(image.pixel_aspect_ratio(), (4, 3));
+
+    Ok(())
+}
+
+#[test]
+fn hdr_metadata_round_trip() -> Result<()> {
+    let mut image = create_image(64, 64)?;
+    assert!(image.content_light_level().is_none());
+    assert!(image.mastering_display_colour_volume().is_none());
+
+    let clli = ContentLightLevel {
+        max_content_light_level: 1000,
+        max_pic_average_light_level: 400,
+    };
+    image.set_content_light_level(clli);
+    // BT.2020 primaries and D65 white point in units of 0.00002
+    let mdcv = MasteringDisplayColourVolume {
+        display_primaries_x: [8500, 6550, 35400],
+        display_primaries_y: [39850, 2300, 14600],
+        white_point_x: 15635,
+        white_point_y: 16450,
+        max_display_mastering_luminance: 10_000_000,
+        min_display_mastering_luminance: 50,
+    };
+    image.set_mastering_display_colour_volume(mdcv);
+
+    let lib_heif = LibHeif::new();
+    let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Av1)?;
+    let mut context = HeifContext::new()?;
+    context.encode_image(&image, &mut encoder, None)?;
+    let buf = context.write_to_bytes()?;
+
+    let context = HeifContext::read_from_bytes(&buf)?;
+    let handle = context.primary_image_handle()?;
+    let image = lib_heif.decode(&handle, ColorSpace::Undefined, None)?;
+    assert_eq!(image.content_light_level(), Some(clli));
+    assert_eq!(image.mastering_display_colour_volume(), Some(mdcv));
+
+    let decoded = mdcv.decode()?;
+    assert!((decoded.white_point_x - 0.3127).abs() < 1e-4);
+    assert!((decoded.max_display_mastering_luminance - 1000.).abs() < 1e-6);
+
+    Ok(())
+}